@@ -41,6 +41,10 @@ pub struct Agent {
     pub(crate) headers: Vec<Header>,
     /// Reused agent state for repeated requests from this agent.
     pub(crate) state: Arc<Mutex<Option<AgentState>>>,
+    /// Copied into each request of this agent, see `drain_limit()`.
+    pub(crate) drain_limit: Option<usize>,
+    /// Copied into each request of this agent, see `default_content_type()`.
+    pub(crate) default_content_type: Option<String>,
 }
 
 /// Container of the state
@@ -66,6 +70,20 @@ impl AgentState {
     pub fn pool(&mut self) -> &mut ConnectionPool {
         &mut self.pool
     }
+
+    /// Add a cookie to the jar. This is the path for both cookies set via the
+    /// agent and the ones received in `Set-Cookie` headers.
+    ///
+    /// A cookie without a domain gets the `host` it was received from.
+    #[cfg(feature = "cookie")]
+    pub(crate) fn add_cookie(&mut self, mut cookie: Cookie<'static>, host: Option<&str>) {
+        if cookie.domain().is_none() {
+            if let Some(host) = host {
+                cookie.set_domain(host.to_string());
+            }
+        }
+        self.jar.add(cookie);
+    }
 }
 
 impl Agent {
@@ -90,6 +108,8 @@ impl Agent {
         Agent {
             headers: self.headers.clone(),
             state: Arc::new(Mutex::new(Some(AgentState::new()))),
+            drain_limit: self.drain_limit,
+            default_content_type: self.default_content_type.clone(),
         }
     }
 
@@ -152,6 +172,34 @@ impl Agent {
         self
     }
 
+    /// The most bytes of an unread response body that are read and thrown away to
+    /// reuse the connection, when the body reader is dropped before the end.
+    ///
+    /// A body with more left than this closes the connection instead. Defaults to 64 kB.
+    ///
+    /// ```
+    /// let agent = ureq::agent()
+    ///     .drain_limit(1024)
+    ///     .build();
+    /// ```
+    pub fn drain_limit(&mut self, bytes: usize) -> &mut Agent {
+        self.drain_limit = Some(bytes);
+        self
+    }
+
+    /// The content type that [`response.content_type()`](struct.Response.html#method.content_type)
+    /// reports for responses without a `Content-Type` header. Defaults to `text/plain`.
+    ///
+    /// ```
+    /// let agent = ureq::agent()
+    ///     .default_content_type("application/json")
+    ///     .build();
+    /// ```
+    pub fn default_content_type(&mut self, content_type: &str) -> &mut Agent {
+        self.default_content_type = Some(content_type.to_string());
+        self
+    }
+
     /// Request by providing the HTTP verb such as `GET`, `POST`...
     ///
     /// ```
@@ -163,7 +211,7 @@ impl Agent {
     /// println!("{:?}", r);
     /// ```
     pub fn request(&self, method: &str, path: &str) -> Request {
-        Request::new(&self, method.into(), path.into())
+        Request::new(self, method.into(), path.into())
     }
 
     /// Gets a cookie in this agent by name. Cookies are available
@@ -186,22 +234,43 @@ impl Agent {
             .cloned()
     }
 
+    /// A snapshot of all cookies in this agent.
+    ///
+    /// ```
+    /// let agent = ureq::agent();
+    ///
+    /// agent.set_cookie(ureq::Cookie::new("name", "value"));
+    ///
+    /// assert_eq!(agent.cookies().len(), 1);
+    /// ```
+    #[cfg(feature = "cookie")]
+    pub fn cookies(&self) -> Vec<Cookie<'static>> {
+        let state = self.state.lock().unwrap();
+        state
+            .as_ref()
+            .map(|state| state.jar.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
     /// Set a cookie in this agent.
     ///
+    /// The cookie is only sent with requests matching its domain, so
+    /// a cookie without a domain is kept, but never sent.
+    ///
     /// ```
     /// let agent = ureq::agent();
     ///
-    /// let cookie = ureq::Cookie::new("name", "value");
+    /// let cookie = ureq::Cookie::build("name", "value")
+    ///     .domain("example.com")
+    ///     .path("/")
+    ///     .finish();
     /// agent.set_cookie(cookie);
     /// ```
     #[cfg(feature = "cookie")]
     pub fn set_cookie(&self, cookie: Cookie<'static>) {
         let mut state = self.state.lock().unwrap();
-        match state.as_mut() {
-            None => (),
-            Some(state) => {
-                state.jar.add_original(cookie);
-            }
+        if let Some(state) = state.as_mut() {
+            state.add_cookie(cookie, None);
         }
     }
 
@@ -1,6 +1,14 @@
 use crate::stream::Stream;
-use chunked_transfer;
-use std::io::{copy, empty, Cursor, Read, Result as IoResult};
+use crate::unit::Unit;
+use std::convert::TryFrom;
+use std::fs::{self, File, OpenOptions};
+use std::io::{
+    copy, empty, Cursor, Error as IoError, ErrorKind, Read, Result as IoResult, Seek, SeekFrom,
+    Write,
+};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[cfg(feature = "charset")]
 use crate::response::DEFAULT_CHARACTER_SET;
@@ -9,10 +17,13 @@ use encoding::label::encoding_from_whatwg_label;
 #[cfg(feature = "charset")]
 use encoding::EncoderTrap;
 
+#[cfg(feature = "gzip")]
+use flate2::write::GzEncoder;
+#[cfg(feature = "gzip")]
+use flate2::Compression;
+
 #[cfg(feature = "json")]
 use super::SerdeValue;
-#[cfg(feature = "json")]
-use serde_json;
 
 /// The different kinds of bodies to send.
 ///
@@ -21,7 +32,7 @@ pub(crate) enum Payload {
     Empty,
     Text(String, String),
     #[cfg(feature = "json")]
-    JSON(SerdeValue),
+    Json(SerdeValue),
     Reader(Box<dyn Read + 'static>),
     Bytes(Vec<u8>),
 }
@@ -32,13 +43,15 @@ impl ::std::fmt::Debug for Payload {
             Payload::Empty => write!(f, "Empty"),
             Payload::Text(t, _) => write!(f, "{}", t),
             #[cfg(feature = "json")]
-            Payload::JSON(_) => write!(f, "JSON"),
+            Payload::Json(_) => write!(f, "JSON"),
             Payload::Reader(_) => write!(f, "Reader"),
             Payload::Bytes(v) => write!(f, "{:?}", v),
         }
     }
 }
 
+// deriving Default for an enum needs a newer compiler than we support.
+#[allow(clippy::derivable_impls)]
 impl Default for Payload {
     fn default() -> Payload {
         Payload::Empty
@@ -63,12 +76,85 @@ impl SizedReader {
     fn new(size: Option<usize>, reader: Box<dyn Read + 'static>) -> Self {
         SizedReader { size, reader }
     }
+
+    /// Read a body of unknown size into memory to find out the size.
+    ///
+    /// With a `max_in_memory` other than 0, a larger body goes into a temporary file.
+    pub(crate) fn into_buffered(mut self, max_in_memory: usize) -> IoResult<SizedReader> {
+        if self.size.is_some() {
+            return Ok(self);
+        }
+        let mut bytes = vec![];
+        if max_in_memory == 0 {
+            self.reader.read_to_end(&mut bytes)?;
+        } else {
+            (&mut self.reader)
+                .take(max_in_memory as u64 + 1)
+                .read_to_end(&mut bytes)?;
+            if bytes.len() > max_in_memory {
+                return spill_to_file(&bytes, self.reader);
+            }
+        }
+        let len = bytes.len();
+        Ok(SizedReader::new(Some(len), Box::new(Cursor::new(bytes))))
+    }
+}
+
+/// Write what has been read so far, and the rest of the body, to a temporary file.
+fn spill_to_file(head: &[u8], mut rest: Box<dyn Read + 'static>) -> IoResult<SizedReader> {
+    let mut file = TempFile::create()?;
+    file.file.write_all(head)?;
+    let len = head.len() as u64 + copy(&mut rest, &mut file.file)?;
+    file.file.seek(SeekFrom::Start(0))?;
+    // on 32-bit targets the file can be longer than a usize counts.
+    let len = usize::try_from(len)
+        .map_err(|_| IoError::new(ErrorKind::InvalidData, "Body too large to buffer"))?;
+    Ok(SizedReader::new(Some(len), Box::new(file)))
+}
+
+/// A file in the temp dir that is removed when dropped.
+struct TempFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl TempFile {
+    fn create() -> IoResult<TempFile> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let path = std::env::temp_dir().join(format!("ureq-body-{}-{}", process::id(), n));
+            // create_new makes sure we never write to a file someone else made.
+            match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => return Ok(TempFile { path, file }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
 }
 
 impl Payload {
     pub fn into_read(self) -> SizedReader {
         match self {
-            Payload::Empty => SizedReader::new(None, Box::new(empty())),
+            Payload::Empty => SizedReader::new(Some(0), Box::new(empty())),
             Payload::Text(text, _charset) => {
                 #[cfg(feature = "charset")]
                 let bytes = {
@@ -84,7 +170,7 @@ impl Payload {
                 SizedReader::new(Some(len), Box::new(cursor))
             }
             #[cfg(feature = "json")]
-            Payload::JSON(v) => {
+            Payload::Json(v) => {
                 let bytes = serde_json::to_vec(&v).expect("Bad JSON in payload");
                 let len = bytes.len();
                 let cursor = Cursor::new(bytes);
@@ -100,18 +186,101 @@ impl Payload {
     }
 }
 
-/// Helper to send a body, either as chunked or not.
-pub(crate) fn send_body(
-    mut body: SizedReader,
-    do_chunk: bool,
-    stream: &mut Stream,
-) -> IoResult<u64> {
-    let n = if do_chunk {
-        let mut chunker = chunked_transfer::Encoder::new(stream);
-        copy(&mut body.reader, &mut chunker)?
+/// Reader over an iterator of byte chunks.
+///
+/// *Internal API*
+pub(crate) struct ChunksReader<I> {
+    chunks: I,
+    current: Cursor<Vec<u8>>,
+}
+
+impl<I: Iterator<Item = Vec<u8>>> ChunksReader<I> {
+    pub(crate) fn new(chunks: I) -> Self {
+        ChunksReader {
+            chunks,
+            current: Cursor::new(vec![]),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Vec<u8>>> Read for ChunksReader<I> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        loop {
+            let amount = self.current.read(buf)?;
+            // an empty chunk is skipped rather than read as the end.
+            if amount > 0 || buf.is_empty() {
+                return Ok(amount);
+            }
+            match self.chunks.next() {
+                Some(chunk) => self.current = Cursor::new(chunk),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// The chunk size used by the encoder unless the request says otherwise.
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 8192;
+
+/// The chunk encoder only makes room for four hex digits of chunk length.
+pub(crate) const MAX_CHUNK_SIZE: usize = 0xffff;
+
+/// Helper to send a body, either as chunked or not, as set up in the unit.
+pub(crate) fn send_body(mut body: SizedReader, unit: &Unit, stream: &mut Stream) -> IoResult<u64> {
+    let n = if unit.is_chunked {
+        let mut chunker = chunked_transfer::Encoder::with_chunks_size(stream, unit.chunk_size);
+        copy_body(
+            &mut body.reader,
+            &mut chunker,
+            unit.is_gzip,
+            unit.flush_body,
+        )?
     } else {
-        copy(&mut body.reader, stream)?
+        copy_body(&mut body.reader, stream, unit.is_gzip, unit.flush_body)?
     };
 
     Ok(n)
 }
+
+/// Copy the body to the writer, optionally gzip compressing it on the way.
+///
+/// The returned amount is the number of uncompressed bytes read from the body.
+fn copy_body<W: Write>(
+    reader: &mut dyn Read,
+    writer: &mut W,
+    _do_gzip: bool,
+    do_flush: bool,
+) -> IoResult<u64> {
+    #[cfg(feature = "gzip")]
+    {
+        if _do_gzip {
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+            let n = copy_flushing(reader, &mut encoder, do_flush)?;
+            encoder.finish()?;
+            return Ok(n);
+        }
+    }
+    copy_flushing(reader, writer, do_flush)
+}
+
+/// Like `io::copy`, but optionally flushes the writer after every read. For a
+/// gzip encoder that is a sync flush, and for the chunk encoder a chunk, which
+/// puts what has been read so far on the wire.
+fn copy_flushing<W: Write>(reader: &mut dyn Read, writer: &mut W, do_flush: bool) -> IoResult<u64> {
+    if !do_flush {
+        return copy(reader, writer);
+    }
+    let mut buf = [0_u8; DEFAULT_CHUNK_SIZE];
+    let mut n = 0;
+    loop {
+        let amount = match reader.read(&mut buf) {
+            Ok(0) => return Ok(n),
+            Ok(amount) => amount,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..amount])?;
+        writer.flush()?;
+        n += amount as u64;
+    }
+}
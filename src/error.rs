@@ -14,6 +14,9 @@ pub enum Error {
     ConnectionFailed(String),
     /// Too many redirects. Synthetic error `500`.
     TooManyRedirects,
+    /// A redirect leads back to an url already visited in the same redirect chain.
+    /// Synthetic error `500`.
+    RedirectLoop(String),
     /// We fail to read the status line. This happens for pooled connections when
     /// TLS fails and we don't notice until trying to read.
     BadStatusRead,
@@ -21,6 +24,12 @@ pub enum Error {
     BadStatus,
     /// A header line that couldn't be parsed. Synthetic error `500`.
     BadHeader,
+    /// The request headers make it ambiguous where the request body ends, like both
+    /// `Content-Length` and `Transfer-Encoding`. The request is not sent.
+    /// Also an HTTP/1.1 response without framing when the request asks for
+    /// [strict framing](struct.Request.html#method.strict_framing).
+    /// Synthetic error `400`.
+    AmbiguousBody(String),
     /// Some unspecified `std::io::Error`. Synthetic error `500`.
     Io(IoError),
 }
@@ -29,9 +38,41 @@ impl Error {
     // If the error is bad status read, which might happen if a TLS connections is
     // closed and we only discover it when trying to read the status line from it.
     pub(crate) fn is_bad_status_read(&self) -> bool {
+        matches!(self, Error::BadStatusRead)
+    }
+
+    /// Whether the url scheme could not be understood, like `ftp://`.
+    ///
+    /// ```
+    /// let err = ureq::get("ftp://example.com/").call_result().unwrap_err();
+    /// assert!(err.is_unknown_scheme());
+    /// ```
+    pub fn is_unknown_scheme(&self) -> bool {
+        matches!(self, Error::UnknownScheme(_))
+    }
+
+    /// The offending scheme, if the url scheme could not be understood.
+    pub fn unknown_scheme(&self) -> Option<&str> {
+        match self {
+            Error::UnknownScheme(scheme) => Some(scheme),
+            _ => None,
+        }
+    }
+
+    /// A copy of the error. An io error keeps its kind and message, but not its source.
+    pub(crate) fn duplicate(&self) -> Error {
         match self {
-            Error::BadStatusRead => true,
-            _ => false,
+            Error::BadUrl(url) => Error::BadUrl(url.clone()),
+            Error::UnknownScheme(scheme) => Error::UnknownScheme(scheme.clone()),
+            Error::DnsFailed(err) => Error::DnsFailed(err.clone()),
+            Error::ConnectionFailed(err) => Error::ConnectionFailed(err.clone()),
+            Error::TooManyRedirects => Error::TooManyRedirects,
+            Error::RedirectLoop(url) => Error::RedirectLoop(url.clone()),
+            Error::BadStatusRead => Error::BadStatusRead,
+            Error::BadStatus => Error::BadStatus,
+            Error::BadHeader => Error::BadHeader,
+            Error::AmbiguousBody(err) => Error::AmbiguousBody(err.clone()),
+            Error::Io(ioe) => Error::Io(IoError::new(ioe.kind(), ioe.to_string())),
         }
     }
 
@@ -43,9 +84,11 @@ impl Error {
             Error::DnsFailed(_) => 400,
             Error::ConnectionFailed(_) => 500,
             Error::TooManyRedirects => 500,
+            Error::RedirectLoop(_) => 500,
             Error::BadStatusRead => 500,
             Error::BadStatus => 500,
             Error::BadHeader => 500,
+            Error::AmbiguousBody(_) => 400,
             Error::Io(_) => 500,
         }
     }
@@ -58,9 +101,11 @@ impl Error {
             Error::DnsFailed(_) => "Dns Failed",
             Error::ConnectionFailed(_) => "Connection Failed",
             Error::TooManyRedirects => "Too Many Redirects",
+            Error::RedirectLoop(_) => "Redirect Loop",
             Error::BadStatusRead => "Failed to read status line",
             Error::BadStatus => "Bad Status",
             Error::BadHeader => "Bad Header",
+            Error::AmbiguousBody(_) => "Ambiguous Body",
             Error::Io(_) => "Network Error",
         }
     }
@@ -73,9 +118,11 @@ impl Error {
             Error::DnsFailed(err) => format!("Dns Failed: {}", err),
             Error::ConnectionFailed(err) => format!("Connection Failed: {}", err),
             Error::TooManyRedirects => "Too Many Redirects".to_string(),
+            Error::RedirectLoop(url) => format!("Redirect Loop: {}", url),
             Error::BadStatusRead => "Failed to read status line".to_string(),
             Error::BadStatus => "Bad Status".to_string(),
             Error::BadHeader => "Bad Header".to_string(),
+            Error::AmbiguousBody(err) => format!("Ambiguous Body: {}", err),
             Error::Io(ioe) => format!("Network Error: {}", ioe),
         }
     }
@@ -42,7 +42,19 @@ impl Header {
     /// assert_eq!("127.0.0.1", header.value());
     /// ```
     pub fn value(&self) -> &str {
-        &self.line.as_str()[self.index + 1..].trim()
+        self.line.as_str()[self.index + 1..].trim()
+    }
+
+    /// Add a folded continuation line to the value, joined with a single space.
+    pub(crate) fn append_folded(&mut self, continuation: &str) {
+        let continuation = continuation.trim();
+        if continuation.is_empty() {
+            return;
+        }
+        let end = self.line.trim_end().len();
+        self.line.truncate(end);
+        self.line.push(' ');
+        self.line.push_str(continuation);
     }
 
     /// Compares the given str to the header name ignoring case.
@@ -58,11 +70,11 @@ impl Header {
     }
 }
 
-pub fn get_header<'a, 'b>(headers: &'b [Header], name: &'a str) -> Option<&'b str> {
+pub fn get_header<'b>(headers: &'b [Header], name: &str) -> Option<&'b str> {
     headers.iter().find(|h| h.is_name(name)).map(|h| h.value())
 }
 
-pub fn get_all_headers<'a, 'b>(headers: &'b [Header], name: &'a str) -> Vec<&'b str> {
+pub fn get_all_headers<'b>(headers: &'b [Header], name: &str) -> Vec<&'b str> {
     headers
         .iter()
         .filter(|h| h.is_name(name))
@@ -82,12 +94,245 @@ pub fn add_header(headers: &mut Vec<Header>, header: Header) {
     headers.push(header);
 }
 
+/// The length from the `Content-Length` headers.
+///
+/// The header may be repeated, or hold a comma separated list, as long as all
+/// values are the same. Differing values are an error.
+pub(crate) fn content_length(headers: &[Header]) -> Result<Option<u64>, ()> {
+    let mut length = None;
+    for value in get_all_headers(headers, "content-length")
+        .iter()
+        .flat_map(|v| v.split(','))
+    {
+        let value = value.trim().parse::<u64>().map_err(|_| ())?;
+        match length {
+            Some(l) if l != value => return Err(()),
+            _ => length = Some(value),
+        }
+    }
+    Ok(length)
+}
+
+/// All transfer codings from the `Transfer-Encoding` headers, lowercased and in order.
+///
+/// `Transfer-Encoding: gzip, chunked` -> `["gzip", "chunked"]`
+pub(crate) fn transfer_codings(headers: &[Header]) -> Vec<String> {
+    codings(headers, "transfer-encoding")
+}
+
+/// All content codings from the `Content-Encoding` headers, lowercased and in order.
+///
+/// `Content-Encoding: deflate, gzip` -> `["deflate", "gzip"]`
+pub(crate) fn content_codings(headers: &[Header]) -> Vec<String> {
+    codings(headers, "content-encoding")
+}
+
+fn codings(headers: &[Header], name: &str) -> Vec<String> {
+    get_all_headers(headers, name)
+        .iter()
+        .flat_map(|v| v.split(','))
+        // drop any parameters, "coding;q=1"
+        .map(|c| {
+            c.split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_ascii_lowercase()
+        })
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// The body is chunked only when chunked is the final transfer coding.
+pub(crate) fn is_chunked(codings: &[String]) -> bool {
+    codings.last().map(|c| c == "chunked").unwrap_or(false)
+}
+
+/// A challenge from a `WWW-Authenticate` header, such as
+/// `Digest realm="example", qop="auth", nonce="abc123"`.
+///
+/// See [`response.auth_challenges()`](struct.Response.html#method.auth_challenges).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthChallenge {
+    scheme: String,
+    token68: Option<String>,
+    params: Vec<(String, String)>,
+}
+
+impl AuthChallenge {
+    /// The auth scheme, like `Basic` or `Digest`.
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Compares the given str to the scheme ignoring case.
+    pub fn is_scheme(&self, other: &str) -> bool {
+        self.scheme.eq_ignore_ascii_case(other)
+    }
+
+    /// A challenge can carry a single opaque (token68) value instead of parameters.
+    pub fn token68(&self) -> Option<&str> {
+        self.token68.as_deref()
+    }
+
+    /// The value of a parameter such as `realm`, with quotes and escapes removed.
+    /// The name is matched ignoring case.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// All parameters as name/value pairs in the order they are given.
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+}
+
+/// Parse the challenges of a `WWW-Authenticate` header value.
+///
+/// There can be several comma separated challenges in one value, and the
+/// parameters of a challenge are also comma separated, so a new challenge
+/// is recognized as a token that isn't followed by `=`.
+pub(crate) fn parse_auth_challenges(value: &str) -> Vec<AuthChallenge> {
+    let mut parser = ChallengeParser {
+        s: value.as_bytes(),
+        pos: 0,
+    };
+    let mut challenges = vec![];
+
+    loop {
+        parser.skip(|c| c == b',' || c == b' ' || c == b'\t');
+        let scheme = parser.token();
+        if scheme.is_empty() {
+            break;
+        }
+        let mut challenge = AuthChallenge {
+            scheme,
+            token68: None,
+            params: vec![],
+        };
+
+        parser.skip_ws();
+        let before = parser.pos;
+        let token = parser.token68();
+        parser.skip_ws();
+        if !token.is_empty() && parser.at_item_end() {
+            // Basic dXNlcjpwYXNz==, or unpadded: Negotiate YII/abc+d
+            challenge.token68 = Some(token);
+        } else {
+            parser.pos = before;
+            loop {
+                let before = parser.pos;
+                let name = parser.token();
+                parser.skip_ws();
+                if name.is_empty() || !parser.eat(b'=') {
+                    // either the end, or the start of the next challenge.
+                    parser.pos = before;
+                    if !name.is_empty() && challenge.params.is_empty() {
+                        // Negotiate abc123
+                        challenge.token68 = Some(name);
+                        parser.skip_ws();
+                    }
+                    break;
+                }
+                parser.skip_ws();
+                let value = parser.value();
+                challenge.params.push((name, value));
+                parser.skip_ws();
+                if !parser.eat(b',') {
+                    break;
+                }
+                parser.skip(|c| c == b',' || c == b' ' || c == b'\t');
+            }
+        }
+
+        challenges.push(challenge);
+    }
+
+    challenges
+}
+
+struct ChallengeParser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ChallengeParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip<F: Fn(u8) -> bool>(&mut self, f: F) {
+        while self.peek().map(&f).unwrap_or(false) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        self.skip(|c| c == b' ' || c == b'\t');
+    }
+
+    fn take<F: Fn(u8) -> bool>(&mut self, f: F) -> String {
+        let from = self.pos;
+        self.skip(f);
+        String::from_utf8_lossy(&self.s[from..self.pos]).to_string()
+    }
+
+    fn at_item_end(&self) -> bool {
+        self.peek().map(|c| c == b',').unwrap_or(true)
+    }
+
+    // https://tools.ietf.org/html/rfc7230#section-3.2.6
+    fn token(&mut self) -> String {
+        self.take(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c))
+    }
+
+    // https://tools.ietf.org/html/rfc7235#section-2.1
+    fn token68(&mut self) -> String {
+        let mut token = self.take(|c| c.is_ascii_alphanumeric() || b"-._~+/".contains(&c));
+        token.push_str(&self.take(|c| c == b'='));
+        token
+    }
+
+    fn value(&mut self) -> String {
+        if !self.eat(b'"') {
+            return self.token();
+        }
+        let mut value = vec![];
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    if let Some(escaped) = self.peek() {
+                        self.pos += 1;
+                        value.push(escaped);
+                    }
+                }
+                _ => value.push(c),
+            }
+        }
+        String::from_utf8_lossy(&value).to_string()
+    }
+}
+
 impl FromStr for Header {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         //
         let line = s.to_string();
-        let index = s.find(':').ok_or_else(|| Error::BadHeader)?;
+        let index = s.find(':').ok_or(Error::BadHeader)?;
 
         // no value?
         if index >= s.len() {
@@ -97,10 +342,7 @@ impl FromStr for Header {
         // https://www.w3.org/Protocols/rfc2616/rfc2616-sec4.html#sec4.2
         // The field value MAY be preceded by any amount of LWS, though a single SP is preferred.
         let value_from = &s[index..];
-        let voff = match value_from.find(|c: char| !c.is_whitespace()) {
-            Some(n) => n,
-            None => 0,
-        };
+        let voff = value_from.find(|c: char| !c.is_whitespace()).unwrap_or(0);
 
         let index = index + voff;
 
@@ -58,13 +58,19 @@
 //! [`.send_json()`](struct.Request.html#method.send_json). In other cases the user
 //! can optionally `request.set("Content-Length", 1234)`.
 //!
+//! An empty body only gets `Content-Length: 0` for methods that are expected to carry
+//! a body (`POST`, `PUT` and `PATCH`). For other methods the header is left out unless
+//! the user sets it.
+//!
 //! For responses, if the `Content-Length` header is present, the methods that reads the
 //! body (as string, json or read trait) are all limited to the length specified in the header.
 //!
 //! # Transfer-Encoding: chunked
 //!
 //! Dechunking is a response body is done automatically if the response headers contains
-//! a `Transfer-Encoding` header.
+//! a `Transfer-Encoding` header where `chunked` is the final encoding. Any other transfer
+//! encoding means the body is read until the server closes the connection. With the
+//! `gzip` feature, `gzip` and `deflate` transfer encodings are also decoded.
 //!
 //! Sending a chunked request body is done by setting the header prior to sending a body.
 //!
@@ -74,6 +80,13 @@
 //!     .send_string("Hello world");
 //! ```
 //!
+//! # Gzip
+//!
+//! By enabling the `ureq = { version = "*", features = ["gzip"] }` feature,
+//! [`request.gzip_body()`](struct.Request.html#method.gzip_body) compresses the
+//! request body and sends it with `Content-Encoding: gzip`. Response bodies with `Content-Encoding: gzip`
+//! or `deflate` are decompressed when read.
+//!
 //! # Character encoding
 //!
 //! By enabling the `ureq = { version = "*", features = ["charset"] }` feature,
@@ -107,9 +120,10 @@ mod test;
 
 pub use crate::agent::Agent;
 pub use crate::error::Error;
-pub use crate::header::Header;
+pub use crate::header::{AuthChallenge, Header};
 pub use crate::request::Request;
-pub use crate::response::Response;
+pub use crate::response::{ContentRange, Event, RedirectStep, Response, Version};
+pub use crate::stream::{Connection, TlsInfo};
 
 // re-export
 #[cfg(feature = "cookie")]
@@ -162,6 +176,14 @@ pub fn trace(path: &str) -> Request {
 }
 
 /// Make an OPTIONS request.
+///
+/// The path `/*` makes an `OPTIONS *` request, which asks about the
+/// capabilities of the server as a whole.
+///
+/// ```
+/// let r = ureq::options("http://example.com/*").call();
+/// println!("{:?}", r);
+/// ```
 pub fn options(path: &str) -> Request {
     request("OPTIONS", path)
 }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
-use std::io::{Read, Result as IoResult};
+use std::io::{ErrorKind, Read, Result as IoResult};
+use std::time::{Duration, Instant};
 
 use crate::stream::Stream;
 use crate::unit::Unit;
@@ -8,12 +9,18 @@ use url::Url;
 
 pub const DEFAULT_HOST: &str = "localhost";
 
+/// How much of an unread body is drained on drop to reuse the connection.
+pub(crate) const DEFAULT_DRAIN_LIMIT: usize = 64 * 1024;
+
+/// How long we wait for the rest of an unread body before giving up on the connection.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// Holder of recycled connections.
 ///
 /// *Internal API*
 #[derive(Default, Debug)]
 pub(crate) struct ConnectionPool {
-    // the actual pooled connection. however only one per hostname:port.
+    // the actual pooled connection. however only one per scheme://hostname:port.
     recycle: HashMap<PoolKey, Stream>,
 }
 
@@ -35,17 +42,27 @@ impl ConnectionPool {
     }
 
     #[cfg(test)]
-    pub fn get(&self, hostname: &str, port: u16) -> Option<&Stream> {
+    pub fn get(&self, scheme: &str, hostname: &str, port: u16) -> Option<&Stream> {
         let key = PoolKey {
+            scheme: scheme.into(),
             hostname: hostname.into(),
             port,
         };
         self.recycle.get(&key)
     }
+
+    #[cfg(test)]
+    pub fn add(&mut self, url: &Url, stream: Stream) {
+        self.recycle.insert(PoolKey::new(url), stream);
+    }
 }
 
+/// A pooled connection can only be reused for the same scheme, since an
+/// `http` connection must never be handed out for an `https` request
+/// (or the other way around), even if the host and port are the same.
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 struct PoolKey {
+    scheme: String,
     hostname: String,
     port: u16,
 }
@@ -64,17 +81,28 @@ impl PoolKey {
             url.port_or_known_default()
         };
         PoolKey {
+            scheme: url.scheme().into(),
             hostname: url.host_str().unwrap_or(DEFAULT_HOST).into(),
             port: port.expect("Failed to get port for pool key"),
         }
     }
 }
 
+/// A body reader that may know how much of the body is left to read.
+///
+/// *Internal API*
+pub(crate) trait BodyRead: Read {
+    /// The number of body bytes left, if the framing tells.
+    fn remaining(&self) -> Option<u64> {
+        None
+    }
+}
+
 /// Read wrapper that returns the stream to the pool once the
 /// read is exhausted (reached a 0).
 ///
 /// *Internal API*
-pub(crate) struct PoolReturnRead<R: Read + Sized> {
+pub(crate) struct PoolReturnRead<R: BodyRead + Sized> {
     // unit that contains the agent where we want to return the reader.
     unit: Option<Unit>,
     // pointer to underlying stream.
@@ -86,7 +114,7 @@ pub(crate) struct PoolReturnRead<R: Read + Sized> {
     reader: Option<R>,
 }
 
-impl<R: Read + Sized> PoolReturnRead<R> {
+impl<R: BodyRead + Sized> PoolReturnRead<R> {
     pub fn new(unit: Option<Unit>, stream: *mut Stream, reader: R) -> Self {
         PoolReturnRead {
             unit,
@@ -120,6 +148,41 @@ impl<R: Read + Sized> PoolReturnRead<R> {
         }
     }
 
+    /// Read and discard what is left of the body, to be able to reuse the connection.
+    ///
+    /// Gives up at once when more than the drain limit of the unit is known to be
+    /// left, else once more than the limit has been read, or when the server is slow
+    /// to send the rest.
+    fn drain(&mut self) -> bool {
+        let limit = match &self.unit {
+            Some(unit) => unit.drain_limit,
+            None => return false,
+        };
+        if self.stream.is_null() {
+            return false;
+        }
+        let remaining = self.reader.as_ref().and_then(|r| r.remaining());
+        if remaining.map(|left| left > limit as u64).unwrap_or(false) {
+            return false;
+        }
+        // the stream goes back to the pool with the unit timeouts reset on reuse.
+        unsafe { (*self.stream).set_read_timeout(Some(DRAIN_TIMEOUT)) };
+        let deadline = Instant::now() + DRAIN_TIMEOUT;
+        let mut buf = [0_u8; 8192];
+        let mut drained = 0;
+        loop {
+            match self.do_read(&mut buf) {
+                Ok(0) => return true,
+                Ok(amount) => drained += amount,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return false,
+            }
+            if drained > limit || Instant::now() >= deadline {
+                return false;
+            }
+        }
+    }
+
     fn do_read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
         match self.reader.as_mut() {
             None => Ok(0),
@@ -128,7 +191,7 @@ impl<R: Read + Sized> PoolReturnRead<R> {
     }
 }
 
-impl<R: Read + Sized> Read for PoolReturnRead<R> {
+impl<R: BodyRead + Sized> Read for PoolReturnRead<R> {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
         let amount = self.do_read(buf)?;
         // only if the underlying reader is exhausted can we send a new
@@ -140,8 +203,35 @@ impl<R: Read + Sized> Read for PoolReturnRead<R> {
     }
 }
 
-impl<R: Read + Sized> Drop for PoolReturnRead<R> {
+impl<R: BodyRead + Sized> Drop for PoolReturnRead<R> {
     fn drop(&mut self) {
-        self.return_connection();
+        if self.drain() {
+            self.return_connection();
+        }
+        // without a unit, or with the body not read to the end, the stream
+        // can't be pooled.
+        if !self.stream.is_null() {
+            self.reader.take();
+            unsafe { drop(Box::from_raw(self.stream)) };
+            self.stream = ::std::ptr::null_mut();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn pool_key_includes_scheme() {
+        let mut pool = ConnectionPool::new();
+        let http = Url::parse("http://example.com:443/").unwrap();
+        let https = Url::parse("https://example.com/").unwrap();
+        pool.add(&http, Stream::Cursor(Cursor::new(vec![])));
+        assert!(pool.get("http", "example.com", 443).is_some());
+        assert!(pool.try_get_connection(&https).is_none());
+        assert!(pool.try_get_connection(&http).is_some());
+        assert_eq!(pool.len(), 0);
     }
 }
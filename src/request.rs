@@ -1,12 +1,13 @@
 use std::io::Read;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use lazy_static::lazy_static;
 use qstring::QString;
 use url::Url;
 
 use crate::agent::{self, Agent, AgentState};
-use crate::body::Payload;
+use crate::body::{self, Payload};
 use crate::error::Error;
 use crate::header::{self, Header};
 use crate::pool;
@@ -17,8 +18,7 @@ use crate::Response;
 use super::SerdeValue;
 
 lazy_static! {
-    static ref URL_BASE: Url =
-        { Url::parse("http://localhost/").expect("Failed to parse URL_BASE") };
+    static ref URL_BASE: Url = Url::parse("http://localhost/").expect("Failed to parse URL_BASE");
 }
 
 /// Request instances are builders that creates a request.
@@ -44,7 +44,21 @@ pub struct Request {
     pub(crate) timeout_connect: u64,
     pub(crate) timeout_read: u64,
     pub(crate) timeout_write: u64,
+    pub(crate) connect_retries: u32,
+    pub(crate) connect_backoff: u64,
     pub(crate) redirects: u32,
+    pub(crate) gzip_body: bool,
+    pub(crate) chunk_size: usize,
+    pub(crate) buffer_body: bool,
+    pub(crate) buffer_body_limit: usize,
+    pub(crate) flush_body: bool,
+    pub(crate) lenient_line_endings: bool,
+    pub(crate) max_line_length: usize,
+    pub(crate) drain_limit: usize,
+    pub(crate) no_cookies: bool,
+    pub(crate) default_content_type: Option<String>,
+    pub(crate) infer_content_type: bool,
+    pub(crate) strict_framing: bool,
 }
 
 impl ::std::fmt::Debug for Request {
@@ -72,6 +86,8 @@ impl Request {
             path,
             headers: agent.headers.clone(),
             redirects: 5,
+            drain_limit: agent.drain_limit.unwrap_or(pool::DEFAULT_DRAIN_LIMIT),
+            default_content_type: agent.default_content_type.clone(),
             ..Default::default()
         }
     }
@@ -94,8 +110,10 @@ impl Request {
     /// Use `.timeout_connect()` and `.timeout_read()` to avoid blocking forever.
     ///
     /// ```
+    /// use std::time::Duration;
+    ///
     /// let r = ureq::get("/my_page")
-    ///     .timeout_connect(10_000) // max 10 seconds
+    ///     .timeout_connect(Duration::from_secs(10)) // max 10 seconds
     ///     .call();
     ///
     /// println!("{:?}", r);
@@ -104,14 +122,65 @@ impl Request {
         self.do_call(Payload::Empty)
     }
 
+    /// Executes the request like [`call()`](#method.call), but only keeps the
+    /// response headers.
+    ///
+    /// The connection is closed as soon as the headers are read, and the body of
+    /// the returned response reads as empty. This is for probes that only look at
+    /// the status and headers, without leaving a half read connection around.
+    ///
+    /// A ["synthetic"](struct.Response.html#method.synthetic) error response keeps
+    /// its body.
+    ///
+    /// ```
+    /// let r = ureq::get("/my_page")
+    ///     .call_head_only();
+    ///
+    /// println!("{:?}", r.header("Content-Type"));
+    /// ```
+    pub fn call_head_only(&mut self) -> Response {
+        let mut resp = self.call();
+        if !resp.synthetic() {
+            crate::response::discard_body(&mut resp);
+        }
+        resp
+    }
+
+    /// Executes the request like [`call()`](#method.call), but returns transport
+    /// level failures as an `Err` instead of a ["synthetic"](struct.Response.html#method.synthetic)
+    /// response.
+    ///
+    /// Genuine HTTP error statuses from the server, such as `404` or `500`, are still
+    /// an `Ok` response.
+    ///
+    /// ```
+    /// let r = ureq::get("borkedscheme://www.google.com")
+    ///     .call_result();
+    ///
+    /// assert!(r.is_err());
+    /// ```
+    pub fn call_result(&mut self) -> Result<Response, Error> {
+        self.do_call_result(Payload::Empty)
+    }
+
     fn do_call(&mut self, payload: Payload) -> Response {
-        self.to_url()
-            .and_then(|url| {
-                let reader = payload.into_read();
-                let unit = Unit::new(&self, &url, true, &reader);
-                unit::connect(&self, unit, true, 0, reader, false)
-            })
-            .unwrap_or_else(|e| e.into())
+        // a synthetic response from connect is kept as is, it carries the url.
+        self.do_connect(payload).unwrap_or_else(|e| e.into())
+    }
+
+    fn do_call_result(&mut self, payload: Payload) -> Result<Response, Error> {
+        self.do_connect(payload).and_then(|resp| resp.into_result())
+    }
+
+    fn do_connect(&mut self, payload: Payload) -> Result<Response, Error> {
+        self.to_url().and_then(|url| {
+            let mut reader = payload.into_read();
+            if self.buffer_body {
+                reader = reader.into_buffered(self.buffer_body_limit)?;
+            }
+            let unit = Unit::new(self, &self.method, &url, true, &reader);
+            unit::connect(self, unit, true, vec![], reader, false)
+        })
     }
 
     /// Send data a json value.
@@ -132,10 +201,10 @@ impl Request {
     /// ```
     #[cfg(feature = "json")]
     pub fn send_json(&mut self, data: SerdeValue) -> Response {
-        if let None = self.header("Content-Type") {
+        if self.header("Content-Type").is_none() {
             self.set("Content-Type", "application/json");
         }
-        self.do_call(Payload::JSON(data))
+        self.do_call(Payload::Json(data))
     }
 
     /// Send data as bytes.
@@ -174,6 +243,9 @@ impl Request {
     /// println!("{:?}", r);
     /// ```
     pub fn send_string(&mut self, data: &str) -> Response {
+        if self.infer_content_type && self.header("Content-Type").is_none() {
+            self.set("Content-Type", "text/plain; charset=utf-8");
+        }
         let text = data.into();
         let charset =
             crate::response::charset_from_content_type(self.header("content-type")).to_string();
@@ -182,7 +254,8 @@ impl Request {
 
     /// Send data from a reader.
     ///
-    /// The `Content-Length` header is not set because we can't know the length of the reader.
+    /// The `Content-Length` header is not set because we can't know the length of the reader,
+    /// unless the body is buffered using [`buffer_body()`](#method.buffer_body).
     ///
     /// ```
     /// use std::io::Cursor;
@@ -198,6 +271,31 @@ impl Request {
         self.do_call(Payload::Reader(Box::new(reader)))
     }
 
+    /// Send data from an iterator of byte chunks.
+    ///
+    /// The total length isn't known up front, so the body is sent using
+    /// `Transfer-Encoding: chunked`, unless the user has set `Transfer-Encoding`
+    /// or `Content-Length`.
+    ///
+    /// ```
+    /// let chunks = vec![b"Hello ".to_vec(), b"there!".to_vec()];
+    ///
+    /// let resp = ureq::post("/somewhere")
+    ///     .set("Content-Type", "text/plain")
+    ///     .send_chunks(chunks);
+    /// ```
+    pub fn send_chunks<I>(&mut self, chunks: I) -> Response
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+        I::IntoIter: 'static,
+    {
+        if !self.has("transfer-encoding") && !self.has("content-length") {
+            self.set("Transfer-Encoding", "chunked");
+        }
+        let reader = body::ChunksReader::new(chunks.into_iter());
+        self.do_call(Payload::Reader(Box::new(reader)))
+    }
+
     /// Set a header field.
     ///
     /// ```
@@ -217,6 +315,115 @@ impl Request {
         self
     }
 
+    /// Send this `Host` header rather than the host of the url, while still connecting
+    /// to the url host. Cookies are matched against and saved for this host.
+    ///
+    /// This is for reaching a virtual host on a server that has no DNS name yet.
+    ///
+    /// ```
+    /// let r = ureq::get("http://127.0.0.1:8080/my_page")
+    ///     .host_header("example.com")
+    ///     .call();
+    /// println!("{:?}", r);
+    /// ```
+    pub fn host_header(&mut self, host: &str) -> &mut Request {
+        self.set("Host", host)
+    }
+
+    /// Make this a conditional request on an earlier response for the same resource.
+    ///
+    /// The `ETag` and `Last-Modified` of the earlier response are sent as
+    /// `If-None-Match` and `If-Modified-Since`. If the resource hasn't changed, the
+    /// server answers [`304 Not Modified`](struct.Response.html#method.not_modified)
+    /// without a body.
+    ///
+    /// ```
+    /// let first = ureq::get("/my_page").call();
+    ///
+    /// let second = ureq::get("/my_page")
+    ///     .conditional_on(&first)
+    ///     .call();
+    ///
+    /// if second.not_modified() {
+    ///     println!("use the body of the first response");
+    /// }
+    /// ```
+    pub fn conditional_on(&mut self, response: &Response) -> &mut Request {
+        if let Some(etag) = response.header("etag") {
+            self.set("If-None-Match", etag);
+        }
+        if let Some(modified) = response.header("last-modified") {
+            self.set("If-Modified-Since", modified);
+        }
+        self
+    }
+
+    /// Add a client address to the `X-Forwarded-For` header, for passing a request on
+    /// from a proxy.
+    ///
+    /// The address is appended to the list of any earlier `X-Forwarded-For` headers,
+    /// which are sent as one comma separated header.
+    ///
+    /// ```
+    /// let req = ureq::get("/my_page")
+    ///     .set("X-Forwarded-For", "203.0.113.7")
+    ///     .forwarded_for("198.51.100.2")
+    ///     .build();
+    /// assert_eq!(req.header("X-Forwarded-For"), Some("203.0.113.7, 198.51.100.2"));
+    /// ```
+    pub fn forwarded_for(&mut self, addr: &str) -> &mut Request {
+        self.append_to_list("X-Forwarded-For", addr)
+    }
+
+    /// Add an element to the `Forwarded` header, like `for=192.0.2.60;proto=http`.
+    ///
+    /// The element is appended to the list of any earlier `Forwarded` headers, which
+    /// are sent as one comma separated header. The element is sent as given, so an
+    /// IPv6 address or a port has to be quoted: `for="[2001:db8::1]:4711"`.
+    ///
+    /// ```
+    /// let req = ureq::get("/my_page")
+    ///     .forwarded("for=192.0.2.60;proto=http")
+    ///     .forwarded("for=198.51.100.17")
+    ///     .build();
+    /// assert_eq!(
+    ///     req.header("Forwarded"),
+    ///     Some("for=192.0.2.60;proto=http, for=198.51.100.17")
+    /// );
+    /// ```
+    pub fn forwarded(&mut self, element: &str) -> &mut Request {
+        self.append_to_list("Forwarded", element)
+    }
+
+    fn append_to_list(&mut self, header: &str, value: &str) -> &mut Request {
+        let mut values: Vec<&str> = self
+            .all(header)
+            .into_iter()
+            .filter(|v| !v.is_empty())
+            .collect();
+        values.push(value);
+        let list = values.join(", ");
+        self.set_header_exact(header, &list)
+    }
+
+    /// Set a header field that is sent with exactly one value and exactly the name
+    /// given, for signing schemes that include the header names as sent.
+    ///
+    /// Unlike [`set()`](#method.set), this replaces any earlier value of the header,
+    /// also `X-` headers and ones set with a different case of the name.
+    ///
+    /// ```
+    /// let r = ureq::get("/my_page")
+    ///     .set("x-amz-date", "20200101T000000Z")
+    ///     .set_header_exact("X-Amz-Date", "20200102T000000Z")
+    ///     .call();
+    /// ```
+    pub fn set_header_exact(&mut self, header: &str, value: &str) -> &mut Request {
+        self.headers.retain(|h| !h.is_name(header));
+        self.headers.push(Header::new(header, value));
+        self
+    }
+
     /// Returns the value for a set header.
     ///
     /// ```
@@ -225,7 +432,7 @@ impl Request {
     ///     .build();
     /// assert_eq!("foobar", req.header("x-api-Key").unwrap());
     /// ```
-    pub fn header<'a>(&self, name: &'a str) -> Option<&str> {
+    pub fn header(&self, name: &str) -> Option<&str> {
         header::get_header(&self.headers, name)
     }
 
@@ -253,7 +460,7 @@ impl Request {
     ///     .build();
     /// assert_eq!(true, req.has("x-api-Key"));
     /// ```
-    pub fn has<'a>(&self, name: &'a str) -> bool {
+    pub fn has(&self, name: &str) -> bool {
         header::has_header(&self.headers, name)
     }
 
@@ -269,7 +476,7 @@ impl Request {
     ///     "2.3.4.5",
     /// ]);
     /// ```
-    pub fn all<'a>(&self, name: &'a str) -> Vec<&str> {
+    pub fn all(&self, name: &str) -> Vec<&str> {
         header::get_all_headers(&self.headers, name)
     }
 
@@ -307,49 +514,96 @@ impl Request {
 
     /// Timeout for the socket connection to be successful.
     ///
-    /// The default is `0`, which means a request can block forever.
+    /// The default is no timeout, which means a request can block forever.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let r = ureq::get("/my_page")
+    ///     .timeout_connect(Duration::from_secs(1)) // wait max 1 second to connect
+    ///     .call();
+    /// println!("{:?}", r);
+    /// ```
+    pub fn timeout_connect(&mut self, timeout: Duration) -> &mut Request {
+        self.timeout_connect = duration_to_millis(timeout);
+        self
+    }
+
+    /// Retry a failed TCP connect, such as a refused connection during a server restart.
+    ///
+    /// There are up to `retries` more attempts after the first. The pause before the
+    /// first retry is `backoff`, and it doubles for every retry after that, up to 30
+    /// seconds. The default is no retries.
+    ///
+    /// A [connect timeout](#method.timeout_connect) covers all attempts, there are no
+    /// retries once it has passed.
     ///
     /// ```
+    /// use std::time::Duration;
+    ///
     /// let r = ureq::get("/my_page")
-    ///     .timeout_connect(1_000) // wait max 1 second to connect
+    ///     .connect_retries(3, Duration::from_millis(100)) // 100ms, 200ms, 400ms
     ///     .call();
     /// println!("{:?}", r);
     /// ```
-    pub fn timeout_connect(&mut self, millis: u64) -> &mut Request {
-        self.timeout_connect = millis;
+    pub fn connect_retries(&mut self, retries: u32, backoff: Duration) -> &mut Request {
+        self.connect_retries = retries;
+        self.connect_backoff = duration_to_millis(backoff);
         self
     }
 
     /// Timeout for the individual reads of the socket.
     ///
-    /// The default is `0`, which means it can block forever.
+    /// The default is no timeout, which means it can block forever.
     ///
     /// ```
+    /// use std::time::Duration;
+    ///
     /// let r = ureq::get("/my_page")
-    ///     .timeout_read(1_000) // wait max 1 second for the read
+    ///     .timeout_read(Duration::from_secs(1)) // wait max 1 second for the read
     ///     .call();
     /// println!("{:?}", r);
     /// ```
-    pub fn timeout_read(&mut self, millis: u64) -> &mut Request {
-        self.timeout_read = millis;
+    pub fn timeout_read(&mut self, timeout: Duration) -> &mut Request {
+        self.timeout_read = duration_to_millis(timeout);
         self
     }
 
     /// Timeout for the individual writes to the socket.
     ///
-    /// The default is `0`, which means it can block forever.
+    /// The default is no timeout, which means it can block forever.
     ///
     /// ```
+    /// use std::time::Duration;
+    ///
     /// let r = ureq::get("/my_page")
-    ///     .timeout_write(1_000)   // wait max 1 second for sending.
+    ///     .timeout_write(Duration::from_secs(1)) // wait max 1 second for sending.
     ///     .call();
     /// println!("{:?}", r);
     /// ```
-    pub fn timeout_write(&mut self, millis: u64) -> &mut Request {
-        self.timeout_write = millis;
+    pub fn timeout_write(&mut self, timeout: Duration) -> &mut Request {
+        self.timeout_write = duration_to_millis(timeout);
         self
     }
 
+    /// Sets the connect, read and write timeouts in one go.
+    ///
+    /// A zero duration means no timeout.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let r = ureq::get("/my_page")
+    ///     .timeout(Duration::from_secs(10))
+    ///     .call();
+    /// println!("{:?}", r);
+    /// ```
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Request {
+        self.timeout_connect(timeout)
+            .timeout_read(timeout)
+            .timeout_write(timeout)
+    }
+
     /// Basic auth.
     ///
     /// These are the same
@@ -382,6 +636,22 @@ impl Request {
         self
     }
 
+    /// Ask for a byte range of the resource, `end` being inclusive.
+    ///
+    /// Sets the `Range: bytes=start-end` header. A server that honors it responds
+    /// with `206 Partial Content` and a `Content-Range` header, see
+    /// [`response.content_range()`](struct.Response.html#method.content_range).
+    ///
+    /// ```
+    /// let req = ureq::get("/my_file")
+    ///     .range(1000, 1999)
+    ///     .build();
+    /// assert_eq!(req.header("Range").unwrap(), "bytes=1000-1999");
+    /// ```
+    pub fn range(&mut self, start: u64, end: u64) -> &mut Request {
+        self.set("Range", &format!("bytes={}-{}", start, end))
+    }
+
     /// How many redirects to follow.
     ///
     /// Defaults to `5`. Set to `0` to avoid redirects and instead
@@ -401,6 +671,195 @@ impl Request {
         self
     }
 
+    /// The size of the chunks when sending a body with `Transfer-Encoding: chunked`.
+    ///
+    /// Defaults to `8192`. Every chunk but the last one is exactly this size, regardless
+    /// of how much the body reader hands over per read. The size is clamped to the
+    /// range `1..=65535`.
+    ///
+    /// ```
+    /// let r = ureq::post("/my_page")
+    ///     .set("Transfer-Encoding", "chunked")
+    ///     .chunk_size(1024)
+    ///     .send_string("Hello World!");
+    /// println!("{:?}", r);
+    /// ```
+    pub fn chunk_size(&mut self, size: usize) -> &mut Request {
+        self.chunk_size = size.clamp(1, body::MAX_CHUNK_SIZE);
+        self
+    }
+
+    /// Read the whole body into memory before sending it, so that a body from a
+    /// reader can be sent with a `Content-Length` header.
+    ///
+    /// This is for servers that don't handle requests without a length, such as
+    /// chunked uploads, and costs holding the entire body in memory. It makes
+    /// no difference to bodies of known length, like strings or bytes.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// let read = Cursor::new("Hello there!\n".to_string().into_bytes());
+    ///
+    /// let r = ureq::post("/my_page")
+    ///     .buffer_body()
+    ///     .send(read);
+    /// println!("{:?}", r);
+    /// ```
+    pub fn buffer_body(&mut self) -> &mut Request {
+        self.buffer_body = true;
+        self
+    }
+
+    /// Like [`buffer_body()`](#method.buffer_body), but a body larger than `bytes` is
+    /// buffered in a temporary file rather than in memory.
+    ///
+    /// The file is removed once the request is done with it.
+    ///
+    /// ```no_run
+    /// let file = std::fs::File::open("upload.bin").unwrap();
+    ///
+    /// let r = ureq::post("/my_page")
+    ///     .buffer_body_limit(1024 * 1024)
+    ///     .send(file);
+    /// println!("{:?}", r);
+    /// ```
+    pub fn buffer_body_limit(&mut self, bytes: usize) -> &mut Request {
+        self.buffer_body = true;
+        self.buffer_body_limit = bytes;
+        self
+    }
+
+    /// Flush the body to the server after every read from the body reader.
+    ///
+    /// By default the body is sent in [chunks](#method.chunk_size) and, with
+    /// [`gzip_body()`](#method.gzip_body), compressed in blocks, which holds back
+    /// data from a slow reader until there is enough of it. With this set, whatever
+    /// a read returns is sent right away, at some cost of compression ratio. This is
+    /// for bodies produced over time, like an event stream.
+    ///
+    /// ```
+    /// let (tx, rx) = std::sync::mpsc::channel::<Vec<u8>>();
+    /// # drop(tx);
+    ///
+    /// let r = ureq::post("/events")
+    ///     .set("Transfer-Encoding", "chunked")
+    ///     .flush_body()
+    ///     .send_chunks(rx);
+    /// println!("{:?}", r);
+    /// ```
+    pub fn flush_body(&mut self) -> &mut Request {
+        self.flush_body = true;
+        self
+    }
+
+    /// Accept response headers that end lines with a bare `\n` instead of `\r\n`.
+    ///
+    /// Some nonconforming servers do this. By default such a response is rejected
+    /// as a malformed status line or header.
+    ///
+    /// ```
+    /// let r = ureq::get("/my_page")
+    ///     .lenient_line_endings()
+    ///     .call();
+    /// println!("{:?}", r);
+    /// ```
+    pub fn lenient_line_endings(&mut self) -> &mut Request {
+        self.lenient_line_endings = true;
+        self
+    }
+
+    /// Set a `Content-Type` that fits the body, unless the header is already set.
+    ///
+    /// With this, [`send_string()`](#method.send_string) sends
+    /// `Content-Type: text/plain; charset=utf-8`.
+    /// [`send_json()`](#method.send_json) always sends `application/json`.
+    ///
+    /// ```
+    /// let r = ureq::post("/my_page")
+    ///     .infer_content_type()
+    ///     .send_string("Hello World!");
+    /// println!("{:?}", r);
+    /// ```
+    pub fn infer_content_type(&mut self) -> &mut Request {
+        self.infer_content_type = true;
+        self
+    }
+
+    /// Neither send the cookies of the agent with this request, nor save the
+    /// cookies the response sets.
+    ///
+    /// ```
+    /// let agent = ureq::agent();
+    ///
+    /// let r = agent.get("/public/logo.png")
+    ///     .no_cookies()
+    ///     .call();
+    /// println!("{:?}", r);
+    /// ```
+    pub fn no_cookies(&mut self) -> &mut Request {
+        self.no_cookies = true;
+        self
+    }
+
+    /// Fail on an HTTP/1.1 response with a body that has neither a `Content-Length` nor
+    /// `Transfer-Encoding: chunked`.
+    ///
+    /// Such a body is read until the server closes the connection, which can't tell a
+    /// complete body from a truncated one. HTTP/1.0 responses are still read that way.
+    ///
+    /// ```
+    /// let r = ureq::get("/my_page")
+    ///     .strict_framing()
+    ///     .call();
+    /// println!("{:?}", r);
+    /// ```
+    pub fn strict_framing(&mut self) -> &mut Request {
+        self.strict_framing = true;
+        self
+    }
+
+    /// The longest status line or header line of the response that is accepted.
+    ///
+    /// A longer line fails the request with a bad status or header error, rather than
+    /// reading an unbounded amount into memory. Defaults to 8 kB.
+    ///
+    /// ```
+    /// let r = ureq::get("/my_page")
+    ///     .max_header_line(16 * 1024)
+    ///     .call();
+    /// println!("{:?}", r);
+    /// ```
+    pub fn max_header_line(&mut self, bytes: usize) -> &mut Request {
+        self.max_line_length = bytes;
+        self
+    }
+
+    /// Compress the request body using gzip.
+    ///
+    /// Requires feature `ureq = { version = "*", features = ["gzip"] }`
+    ///
+    /// Sets the `Content-Encoding: gzip` header unless already set. Since the
+    /// compressed size isn't known up front, the body is sent using
+    /// `Transfer-Encoding: chunked` and no `Content-Length` header is set. A request
+    /// without a body, like the `GET` after a redirect, is sent as is.
+    ///
+    /// A request that also sets a `Content-Length`, or a `Transfer-Encoding` that
+    /// doesn't end with `chunked`, is not sent and gets an
+    /// [`Error::AmbiguousBody`](enum.Error.html#variant.AmbiguousBody).
+    ///
+    /// ```
+    /// let r = ureq::post("/my_page")
+    ///     .gzip_body()
+    ///     .send_string("Hello World!");
+    /// println!("{:?}", r);
+    /// ```
+    #[cfg(feature = "gzip")]
+    pub fn gzip_body(&mut self) -> &mut Request {
+        self.gzip_body = true;
+        self
+    }
+
     // pub fn retry(&self, times: u16) -> Request {
     //     unimplemented!()
     // }
@@ -515,3 +974,14 @@ impl Request {
             .map_err(|e| Error::BadUrl(format!("{}", e)))
     }
 }
+
+/// Internally timeouts are millis where `0` means no timeout. Round up any
+/// sub-millisecond duration so it doesn't accidentally turn into "no timeout".
+fn duration_to_millis(timeout: Duration) -> u64 {
+    let millis = timeout.as_millis() as u64;
+    if millis == 0 && timeout > Duration::from_secs(0) {
+        1
+    } else {
+        millis
+    }
+}
@@ -1,16 +1,20 @@
-use std::io::{Cursor, Error as IoError, ErrorKind, Read, Result as IoResult};
+use std::io::{
+    self, BufRead, BufReader, Cursor, Error as IoError, ErrorKind, Read, Result as IoResult, Write,
+};
+use std::net::SocketAddr;
 use std::str::FromStr;
+use std::time::Duration;
 
 use chunked_transfer::Decoder as ChunkDecoder;
 
 use crate::error::Error;
-use crate::header::Header;
-use crate::pool::PoolReturnRead;
-use crate::stream::Stream;
-use crate::unit::Unit;
+use crate::header::{self, AuthChallenge, Header};
 
-#[cfg(feature = "json")]
-use serde_json;
+use crate::pool::{BodyRead, PoolReturnRead};
+use crate::stream::{Connection, Stream, TlsInfo};
+use crate::unit::Unit;
+#[cfg(feature = "gzip")]
+use flate2::read::{GzDecoder, ZlibDecoder};
 
 #[cfg(feature = "charset")]
 use encoding::label::encoding_from_whatwg_label;
@@ -45,6 +49,71 @@ pub struct Response {
     headers: Vec<Header>,
     unit: Option<Unit>,
     stream: Option<Stream>,
+    redirects: Vec<RedirectStep>,
+}
+
+/// The http version of a response.
+///
+/// See [`response.status_parts()`](struct.Response.html#method.status_parts).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// `HTTP/1.0`
+    Http10,
+    /// `HTTP/1.1`
+    Http11,
+    /// `HTTP/2`
+    Http2,
+    /// Any other version. The text is in
+    /// [`response.http_version()`](struct.Response.html#method.http_version).
+    Unknown,
+}
+
+impl Version {
+    fn from_status_line(version: &str) -> Version {
+        match version.to_ascii_uppercase().as_str() {
+            "HTTP/1.0" => Version::Http10,
+            "HTTP/1.1" => Version::Http11,
+            "HTTP/2" | "HTTP/2.0" => Version::Http2,
+            _ => Version::Unknown,
+        }
+    }
+}
+
+/// A redirect that was followed on the way to a response.
+///
+/// See [`response.redirects()`](struct.Response.html#method.redirects).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectStep {
+    /// The redirect status, like `301`.
+    pub status: u16,
+    /// The url that answered with the redirect.
+    pub from: String,
+    /// The url the `Location` header resolved to.
+    pub to: String,
+}
+
+/// A server-sent event from a `text/event-stream` body.
+///
+/// See [`response.into_events()`](struct.Response.html#method.into_events).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// The last event id the server set, which carries over to later events.
+    pub id: Option<String>,
+    /// The event type, `message` unless the server says otherwise.
+    pub event: String,
+    /// The data lines of the event, joined with `\n`.
+    pub data: String,
+}
+
+/// A parsed `Content-Range` header, such as `Content-Range: bytes 1000-1999/5000`.
+///
+/// `end` is inclusive and `total` is `None` when the server doesn't know the
+/// complete length (`bytes 1000-1999/*`).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ContentRange {
+    pub start: u64,
+    pub end: u64,
+    pub total: Option<u64>,
 }
 
 /// index into status_line where we split: HTTP/1.1 200 OK
@@ -86,6 +155,9 @@ impl Response {
 
     /// The URL we ended up at. This can differ from the request url when
     /// we have followed redirects.
+    ///
+    /// After a redirect, this is the URL resolved from the `Location` header,
+    /// including any query string it carries.
     pub fn get_url(&self) -> &str {
         self.url.as_ref().map(|s| &s[..]).unwrap_or("")
     }
@@ -107,11 +179,31 @@ impl Response {
 
     /// The status text: `OK`
     pub fn status_text(&self) -> &str {
-        &self.status_line.as_str()[self.index.response_code + 1..].trim()
+        self.status_line.as_str()[self.index.response_code + 1..].trim()
+    }
+
+    /// The http version, status and status text all at once:
+    /// `(Version::Http11, 200, "OK")`
+    ///
+    /// ```
+    /// use ureq::Version;
+    ///
+    /// let resp = ureq::Response::new(301, "Moved", "");
+    /// let (version, status, text) = resp.status_parts();
+    /// assert_eq!(version, Version::Http11);
+    /// assert_eq!(status, 301);
+    /// assert_eq!(text, "Moved");
+    /// ```
+    pub fn status_parts(&self) -> (Version, u16, &str) {
+        (
+            Version::from_status_line(self.http_version()),
+            self.status(),
+            self.status_text(),
+        )
     }
 
     /// The header corresponding header value for the give name, if any.
-    pub fn header<'a>(&self, name: &'a str) -> Option<&str> {
+    pub fn header(&self, name: &str) -> Option<&str> {
         self.headers
             .iter()
             .find(|h| h.is_name(name))
@@ -128,12 +220,12 @@ impl Response {
     }
 
     /// Tells if the response has the named header.
-    pub fn has<'a>(&self, name: &'a str) -> bool {
+    pub fn has(&self, name: &str) -> bool {
         self.header(name).is_some()
     }
 
     /// All headers corresponding values for the give name, or empty vector.
-    pub fn all<'a>(&self, name: &'a str) -> Vec<&str> {
+    pub fn all(&self, name: &str) -> Vec<&str> {
         self.headers
             .iter()
             .filter(|h| h.is_name(name))
@@ -150,6 +242,13 @@ impl Response {
         self.status >= 300 && self.status <= 399
     }
 
+    /// Whether the response status is `304 Not Modified`, the answer to a
+    /// [conditional request](struct.Request.html#method.conditional_on) when the
+    /// earlier response is still current.
+    pub fn not_modified(&self) -> bool {
+        self.status == 304
+    }
+
     /// Whether the response status is: 400 <= status <= 499
     pub fn client_error(&self) -> bool {
         self.status >= 400 && self.status <= 499
@@ -209,9 +308,78 @@ impl Response {
         &self.error
     }
 
+    /// The redirects followed on the way to this response, in order.
+    ///
+    /// Empty when there were no redirects.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// let resp = ureq::get("http://example.com/old").call();
+    /// for step in resp.redirects() {
+    ///     println!("{} {} -> {}", step.status, step.from, step.to);
+    /// }
+    /// ```
+    pub fn redirects(&self) -> &[RedirectStep] {
+        &self.redirects
+    }
+
+    /// The TLS version and cipher suite of the connection, for https requests.
+    ///
+    /// `None` for plain http, and after the body reader has taken the connection.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// let resp = ureq::get("https://www.google.com/").call();
+    /// let info = resp.tls_info().unwrap();
+    /// println!("{} {}", info.version(), info.cipher_suite());
+    /// ```
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        self.stream.as_ref().and_then(|s| s.tls_info())
+    }
+
+    /// The address of the server that answered, which tells which of several
+    /// addresses for a host name was used.
+    ///
+    /// `None` after the body reader has taken the connection.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// let resp = ureq::get("http://www.google.com/").call();
+    /// println!("{:?}", resp.remote_addr());
+    /// ```
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.stream.as_ref().and_then(|s| s.remote_addr())
+    }
+
+    /// The challenges of all `WWW-Authenticate` headers, typically sent with a `401`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let s = "HTTP/1.1 401 Unauthorized\r\n\
+    ///     WWW-Authenticate: Basic realm=\"simple\"\r\n\
+    ///     \r\n";
+    /// let resp = s.parse::<ureq::Response>().unwrap();
+    /// let challenges = resp.auth_challenges();
+    /// assert_eq!(challenges[0].scheme(), "Basic");
+    /// assert_eq!(challenges[0].param("realm"), Some("simple"));
+    /// ```
+    pub fn auth_challenges(&self) -> Vec<AuthChallenge> {
+        self.all("www-authenticate")
+            .iter()
+            .flat_map(|v| header::parse_auth_challenges(v))
+            .collect()
+    }
+
     /// The content type part of the "Content-Type" header without
     /// the charset.
     ///
+    /// Without the header, this is the agent's
+    /// [default content type](struct.Agent.html#method.default_content_type), or `text/plain`.
+    ///
     /// Example:
     ///
     /// ```
@@ -227,6 +395,11 @@ impl Response {
                     .map(|index| &header[0..index])
                     .unwrap_or(header)
             })
+            .or_else(|| {
+                self.unit
+                    .as_ref()
+                    .and_then(|u| u.default_content_type.as_deref())
+            })
             .unwrap_or(DEFAULT_CONTENT_TYPE)
     }
 
@@ -243,6 +416,37 @@ impl Response {
         charset_from_content_type(self.header("content-type"))
     }
 
+    /// The parsed `Content-Range` header of a `206 Partial Content` response.
+    ///
+    /// Is `None` if the header is missing, isn't a `bytes` range or can't be parsed.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let s = "HTTP/1.1 206 Partial Content\r\n\
+    ///     Content-Range: bytes 10-19/100\r\n\
+    ///     \r\n";
+    /// let resp = s.parse::<ureq::Response>().unwrap();
+    /// let range = resp.content_range().unwrap();
+    /// assert_eq!((range.start, range.end, range.total), (10, 19, Some(100)));
+    /// ```
+    pub fn content_range(&self) -> Option<ContentRange> {
+        let header = self.header("content-range")?;
+        let rest = header.trim().strip_prefix("bytes ")?;
+        let (range, total) = rest.split_once('/')?;
+        let (start, end) = range.split_once('-')?;
+        let start = start.trim().parse::<u64>().ok()?;
+        let end = end.trim().parse::<u64>().ok()?;
+        let total = match total.trim() {
+            "*" => None,
+            t => Some(t.parse::<u64>().ok()?),
+        };
+        if end < start {
+            return None;
+        }
+        Some(ContentRange { start, end, total })
+    }
+
     /// Turn this response into a `impl Read` of the body.
     ///
     /// 1. If `Transfer-Encoding: chunked`, the returned reader will unchunk it
@@ -251,6 +455,9 @@ impl Response {
     ///    length regardless of how many bytes the server sends.
     /// 3. If no length header, the reader is until server stream end.
     ///
+    /// With the `gzip` feature, a body with `Content-Encoding: gzip` or `deflate` is
+    /// decompressed by the returned reader.
+    ///
     /// Example:
     ///
     /// ```
@@ -271,42 +478,157 @@ impl Response {
     /// assert_eq!(bytes.len(), len);
     /// ```
     pub fn into_reader(self) -> impl Read {
-        //
+        self.body_reader(true)
+    }
 
-        let is_http10 = self.http_version().eq_ignore_ascii_case("HTTP/1.0");
-        let is_close = self
-            .header("connection")
-            .map(|c| c.eq_ignore_ascii_case("close"))
-            .unwrap_or(false);
+    /// Turn this response into a `impl Read` of the body as the server sent it.
+    ///
+    /// Like [`into_reader()`](#method.into_reader), this reads the body unchunked and
+    /// limited to the `Content-Length`, but any `Content-Encoding` is left as is.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use std::io::Read;
+    ///
+    /// let resp = ureq::get("http://example.com/archive.tar.gz").call();
+    ///
+    /// let mut bytes = vec![];
+    /// resp.into_raw_reader().read_to_end(&mut bytes).unwrap();
+    /// ```
+    pub fn into_raw_reader(self) -> impl Read {
+        self.body_reader(false)
+    }
+
+    /// Like [`into_reader()`](#method.into_reader), but calls `progress` after every read
+    /// of the body with the number of bytes read so far and, when known, the total.
+    ///
+    /// The total is the `Content-Length`, which is left out for a compressed body since it
+    /// isn't the length of what is read.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use std::io::Read;
+    ///
+    /// let resp = ureq::get("http://example.com/big.iso").call();
+    ///
+    /// let mut reader = resp.into_reader_with_progress(|read, total| match total {
+    ///     Some(total) => println!("{} of {} bytes", read, total),
+    ///     None => println!("{} bytes", read),
+    /// });
+    /// let mut bytes = vec![];
+    /// reader.read_to_end(&mut bytes).unwrap();
+    /// ```
+    pub fn into_reader_with_progress<F>(self, progress: F) -> impl Read
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        let total = if header::content_codings(&self.headers).is_empty() {
+            header::content_length(&self.headers).ok().flatten()
+        } else {
+            None
+        };
+        ProgressRead {
+            reader: self.into_reader(),
+            progress,
+            read: 0,
+            total,
+        }
+    }
 
-        let is_head = (&self.unit).as_ref().map(|u| u.is_head()).unwrap_or(false);
+    /// Whether the body is chunked, and otherwise its length. No length means the
+    /// body goes on until the connection is closed.
+    fn body_framing(&self) -> (bool, Option<u64>) {
+        let is_http10 = self.is_http10();
 
-        let is_chunked = self
-            .header("transfer-encoding")
-            .map(|enc| !enc.is_empty()) // whatever it says, do chunked
-            .unwrap_or(false);
+        let is_head = self.unit.as_ref().map(|u| u.is_head()).unwrap_or(false);
+
+        let codings = header::transfer_codings(&self.headers);
+        let is_chunked = header::is_chunked(&codings);
 
         let use_chunked = !is_http10 && !is_head && is_chunked;
 
-        let limit_bytes = if is_http10 || is_close {
-            None
-        } else if is_head {
-            // head requests never have a body
+        let limit_bytes = if is_head || self.status == 204 || self.status == 304 {
+            // these never have a body, whatever the headers say.
             Some(0)
+        } else if !codings.is_empty() && !is_chunked {
+            // without chunked as the final encoding, the body ends where the
+            // connection does, no matter the content-length.
+            None
         } else {
-            self.header("content-length")
-                .and_then(|l| l.parse::<usize>().ok())
+            // also when the connection closes after the response, to notice
+            // a body that is cut short.
+            header::content_length(&self.headers).ok().flatten()
         };
 
+        (use_chunked, limit_bytes)
+    }
+
+    /// Take the connection of the response, to speak some other protocol on it,
+    /// such as after a `101 Switching Protocols`.
+    ///
+    /// The connection is only handed over when no body is left to read on it, as
+    /// after a `101`, or with a `Content-Length: 0`, or for a `HEAD` request. A
+    /// body is never read and thrown away here, so `None` if there is a body.
+    /// `None` also when there is no connection, like for a
+    /// ["synthetic"](#method.synthetic) response.
+    ///
+    /// The connection doesn't go back to the pool.
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    ///
+    /// let resp = ureq::get("http://example.com/chat")
+    ///     .set("Connection", "Upgrade")
+    ///     .set("Upgrade", "my-protocol")
+    ///     .call();
+    ///
+    /// if resp.status() == 101 {
+    ///     let mut conn = resp.into_stream().unwrap();
+    ///     conn.write_all(b"hello\n").unwrap();
+    /// }
+    /// ```
+    pub fn into_stream(mut self) -> Option<Connection> {
+        // after switching protocols, there is no body.
+        let no_body = self.status == 101 || self.body_framing() == (false, Some(0));
+        if !no_body {
+            return None;
+        }
+        let stream = self.stream.take()?;
+        if !stream.is_poolable() {
+            return None;
+        }
+        Some(Connection::new(stream))
+    }
+
+    fn body_reader(self, decode_content: bool) -> Box<dyn Read> {
+        //
+
+        if self.stream.is_none() {
+            // the body was discarded with the connection.
+            return Box::new(io::empty());
+        }
+
+        let is_reusable = self.is_connection_reusable();
+        let is_head = self.unit.as_ref().map(|u| u.is_head()).unwrap_or(false);
+
+        let codings = header::transfer_codings(&self.headers);
+        let content_codings = header::content_codings(&self.headers);
+
+        let (use_chunked, limit_bytes) = self.body_framing();
+
         let stream = Box::new(self.stream.expect("No reader in response?!"));
         let stream_ptr = Box::into_raw(stream);
         let mut reclaiming_read = ReclaimingRead {
             stream: stream_ptr,
             dealloc: false,
         };
-        let unit = self.unit;
+        // a connection that isn't reusable must not go back to the pool,
+        // not even after an empty HEAD body.
+        let unit = if is_reusable { self.unit } else { None };
 
-        match (use_chunked, limit_bytes) {
+        let reader = match (use_chunked, limit_bytes) {
             (true, _) => Box::new(PoolReturnRead::new(
                 unit,
                 stream_ptr,
@@ -321,7 +643,95 @@ impl Response {
                 reclaiming_read.dealloc = true; // dealloc when read drops.
                 Box::new(reclaiming_read)
             }
+        };
+
+        if is_head || limit_bytes == Some(0) {
+            return reader;
         }
+        let reader = decode_codings(reader, &codings);
+        if decode_content {
+            decode_codings(reader, &content_codings)
+        } else {
+            reader
+        }
+    }
+
+    /// Like [`into_reader()`](#method.into_reader), but every read of the body gives up
+    /// with an error after waiting `timeout` for data from the server.
+    ///
+    /// This replaces the [read timeout](struct.Request.html#method.timeout_read) of the
+    /// request for the body. A zero duration means no timeout.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use std::io::Read;
+    /// use std::time::Duration;
+    ///
+    /// let resp = ureq::get("http://example.com/stream").call();
+    ///
+    /// let mut reader = resp.into_reader_with_timeout(Duration::from_secs(5));
+    /// let mut bytes = vec![];
+    /// reader.read_to_end(&mut bytes).expect("stalled for 5 seconds");
+    /// ```
+    pub fn into_reader_with_timeout(self, timeout: Duration) -> impl Read {
+        if let Some(stream) = &self.stream {
+            stream.set_read_timeout(Some(timeout).filter(|t| *t > Duration::from_secs(0)));
+        }
+        self.into_reader()
+    }
+
+    fn is_http10(&self) -> bool {
+        self.http_version().eq_ignore_ascii_case("HTTP/1.0")
+    }
+
+    /// Whether the `Connection` header lists the given token.
+    fn has_connection_token(&self, token: &str) -> bool {
+        self.all("connection")
+            .iter()
+            .flat_map(|v| v.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    }
+
+    /// An HTTP/1.1 response with a body neither chunked nor of known length, which
+    /// means reading it until the server closes the connection.
+    pub(crate) fn is_unframed(&self, is_head: bool) -> bool {
+        let no_body = is_head || self.status < 200 || self.status == 204 || self.status == 304;
+        let codings = header::transfer_codings(&self.headers);
+        !self.is_http10()
+            && !no_body
+            && !header::is_chunked(&codings)
+            && header::content_length(&self.headers) == Ok(None)
+    }
+
+    /// HTTP/1.1 connections are persistent unless the server says `Connection: close`,
+    /// HTTP/1.0 connections are closed unless the server says `Connection: keep-alive`.
+    fn is_connection_reusable(&self) -> bool {
+        if self.is_http10() {
+            self.has_connection_token("keep-alive")
+        } else {
+            !self.has_connection_token("close")
+        }
+    }
+
+    /// Copy the response body to a writer, returning the number of bytes copied.
+    ///
+    /// The body is read the same way as [`into_reader()`](#method.into_reader), but
+    /// streamed straight to the writer without buffering it all in memory.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let resp =
+    ///     ureq::get("https://ureq.s3.eu-central-1.amazonaws.com/hello_world.json")
+    ///         .call();
+    ///
+    /// let mut file = std::io::sink();
+    /// let len = resp.copy_to(&mut file);
+    /// ```
+    pub fn copy_to<W: Write>(self, w: &mut W) -> IoResult<u64> {
+        let mut reader = self.into_reader();
+        io::copy(&mut reader, w)
     }
 
     /// Turn this response into a String of the response body. By default uses `utf-8`,
@@ -370,6 +780,69 @@ impl Response {
         }
     }
 
+    /// Turn this response into an iterator over the lines of the response body.
+    ///
+    /// Lines end with `\n` or `\r\n`, which are not part of the yielded strings, and
+    /// the last line doesn't need a line ending, like for `BufRead::lines`. With the
+    /// `charset` feature, each line is decoded like [`into_string()`](#method.into_string)
+    /// does for the entire body.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// let resp = ureq::get("http://example.com/log").call();
+    ///
+    /// for line in resp.into_lines() {
+    ///     println!("{}", line.unwrap());
+    /// }
+    /// ```
+    pub fn into_lines(self) -> impl Iterator<Item = IoResult<String>> {
+        #[cfg(feature = "charset")]
+        let encoding = encoding_from_whatwg_label(self.charset())
+            .or_else(|| encoding_from_whatwg_label(DEFAULT_CHARACTER_SET))
+            .unwrap();
+        let reader = BufReader::new(self.into_reader());
+        reader.split(b'\n').map(move |line| {
+            let mut line = line?;
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            #[cfg(feature = "charset")]
+            {
+                Ok(encoding.decode(&line, DecoderTrap::Replace).unwrap())
+            }
+            #[cfg(not(feature = "charset"))]
+            {
+                Ok(String::from_utf8_lossy(&line).to_string())
+            }
+        })
+    }
+
+    /// Turn this response into an iterator over the server-sent events of a
+    /// `text/event-stream` body.
+    ///
+    /// Events are read as they arrive, which makes this work for streams that stay open.
+    /// Comments and events without data are skipped, as the SSE spec says.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// let resp = ureq::get("http://example.com/events")
+    ///     .set("Accept", "text/event-stream")
+    ///     .call();
+    ///
+    /// for event in resp.into_events() {
+    ///     let event = event.unwrap();
+    ///     println!("{}: {}", event.event, event.data);
+    /// }
+    /// ```
+    pub fn into_events(self) -> impl Iterator<Item = IoResult<Event>> {
+        EventIter {
+            lines: self.into_lines(),
+            last_id: None,
+        }
+    }
+
     /// Turn this response into a (serde) JSON value of the response body.
     ///
     /// Requires feature `ureq = { version = "*", features = ["json"] }`
@@ -388,12 +861,37 @@ impl Response {
     #[cfg(feature = "json")]
     pub fn into_json(self) -> IoResult<serde_json::Value> {
         let reader = self.into_reader();
-        serde_json::from_reader(reader).map_err(|e| {
-            IoError::new(
-                ErrorKind::InvalidData,
-                format!("Failed to read JSON: {}", e),
-            )
-        })
+        read_json(reader)
+    }
+
+    /// Like [`into_json()`](#method.into_json), but fails once the body is larger than
+    /// `limit` bytes, rather than reading an unbounded amount into memory.
+    ///
+    /// Requires feature `ureq = { version = "*", features = ["json"] }`
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let s = "HTTP/1.1 200 OK\r\n\r\n{\"hello\":\"world\"}";
+    /// let resp = s.parse::<ureq::Response>().unwrap();
+    ///
+    /// let err = resp.into_json_with_limit(10).unwrap_err();
+    /// assert!(err.to_string().contains("larger than the limit"));
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn into_json_with_limit(self, limit: usize) -> IoResult<serde_json::Value> {
+        if let Ok(Some(len)) = header::content_length(&self.headers) {
+            // no point in reading what we know is too much.
+            if len > limit as u64 {
+                return Err(body_too_large(limit));
+            }
+        }
+        let reader = SizeGuard {
+            reader: self.into_reader(),
+            limit,
+            left: limit,
+        };
+        read_json(reader)
     }
 
     /// Create a response from a Read trait impl.
@@ -415,10 +913,62 @@ impl Response {
         Self::do_from_read(reader).unwrap_or_else(|e| e.into())
     }
 
-    fn do_from_read(mut reader: impl Read) -> Result<Response, Error> {
+    /// Make a copy of a response that is held in memory, such as one made with
+    /// [`Response::new()`](#method.new) or parsed from a string, to reuse it in tests.
+    ///
+    /// The body of the copy starts where the body of this response is at. A response
+    /// with a body still to be read from the network can't be copied.
+    ///
+    /// ```
+    /// let resp = ureq::Response::new(401, "Authorization Required", "Please log in");
+    /// let copy = resp.try_clone().unwrap();
+    ///
+    /// assert_eq!(copy.status(), 401);
+    /// assert_eq!(resp.into_string().unwrap(), copy.into_string().unwrap());
+    /// ```
+    pub fn try_clone(&self) -> IoResult<Response> {
+        let stream = match &self.stream {
+            None => None,
+            Some(Stream::Cursor(cursor)) => {
+                let mut copy = Cursor::new(cursor.get_ref().clone());
+                copy.set_position(cursor.position());
+                Some(Stream::Cursor(copy))
+            }
+            Some(_) => {
+                return Err(IoError::new(
+                    ErrorKind::Unsupported,
+                    "Can't clone a response with its body on the network",
+                ))
+            }
+        };
+        Ok(Response {
+            url: self.url.clone(),
+            error: self.error.as_ref().map(|e| e.duplicate()),
+            status_line: self.status_line.clone(),
+            index: self.index,
+            status: self.status,
+            headers: self.headers.clone(),
+            unit: None,
+            stream,
+            redirects: self.redirects.clone(),
+        })
+    }
+
+    /// Like `from_read`, with the parsing options of a request.
+    ///
+    /// *Internal API*
+    pub(crate) fn from_read_with(reader: impl Read, opts: ParseOptions) -> Self {
+        Self::do_from_read_with(reader, opts).unwrap_or_else(|e| e.into())
+    }
+
+    fn do_from_read(reader: impl Read) -> Result<Response, Error> {
+        Self::do_from_read_with(reader, ParseOptions::default())
+    }
+
+    fn do_from_read_with(mut reader: impl Read, opts: ParseOptions) -> Result<Response, Error> {
         //
         // HTTP/1.1 200 OK\r\n
-        let status_line = read_next_line(&mut reader).map_err(|e| match e.kind() {
+        let status_line = read_next_line(&mut reader, opts).map_err(|e| match e.kind() {
             ErrorKind::ConnectionAborted => Error::BadStatusRead,
             _ => Error::BadStatus,
         })?;
@@ -426,16 +976,36 @@ impl Response {
         let (index, status) = parse_status_line(status_line.as_str())?;
 
         let mut headers: Vec<Header> = Vec::new();
+        // whether the previous line was a header a continuation line belongs to.
+        let mut can_fold = false;
         loop {
-            let line = read_next_line(&mut reader).map_err(|_| Error::BadHeader)?;
+            let line = read_next_line(&mut reader, opts).map_err(|_| Error::BadHeader)?;
             if line.is_empty() {
                 break;
             }
+            // obs-fold: a line starting with whitespace continues the value of
+            // the header before it.
+            if line.starts_with(' ') || line.starts_with('\t') {
+                if can_fold {
+                    if let Some(header) = headers.last_mut() {
+                        header.append_folded(&line);
+                    }
+                }
+                continue;
+            }
+            can_fold = false;
             if let Ok(header) = line.as_str().parse::<Header>() {
                 headers.push(header);
+                can_fold = true;
             }
         }
 
+        // differing lengths make it ambiguous where the body ends, which is
+        // how responses get smuggled onto a connection.
+        if header::content_length(&headers).is_err() {
+            return Err(Error::BadHeader);
+        }
+
         Ok(Response {
             url: None,
             error: None,
@@ -445,9 +1015,20 @@ impl Response {
             headers,
             unit: None,
             stream: None,
+            redirects: vec![],
         })
     }
 
+    /// Turn a synthetic response back into the error it was made from.
+    ///
+    /// *Internal API*
+    pub(crate) fn into_result(mut self) -> Result<Response, Error> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => Ok(self),
+        }
+    }
+
     #[cfg(test)]
     pub fn to_write_vec(&self) -> Vec<u8> {
         self.stream.as_ref().unwrap().to_write_vec()
@@ -460,13 +1041,13 @@ fn parse_status_line(line: &str) -> Result<(ResponseStatusIndex, u16), Error> {
 
     let mut split = line.splitn(3, ' ');
 
-    let http_version = split.next().ok_or_else(|| Error::BadStatus)?;
+    let http_version = split.next().ok_or(Error::BadStatus)?;
     if http_version.len() < 5 {
         return Err(Error::BadStatus);
     }
     let index1 = http_version.len();
 
-    let status = split.next().ok_or_else(|| Error::BadStatus)?;
+    let status = split.next().ok_or(Error::BadStatus)?;
     if status.len() < 2 {
         return Err(Error::BadStatus);
     }
@@ -508,17 +1089,24 @@ impl FromStr for Response {
     }
 }
 
-impl Into<Response> for Error {
-    fn into(self) -> Response {
-        let status = self.status();
-        let status_text = self.status_text().to_string();
-        let body_text = self.body_text();
+impl From<Error> for Response {
+    fn from(err: Error) -> Response {
+        let status = err.status();
+        let status_text = err.status_text().to_string();
+        let body_text = err.body_text();
         let mut resp = Response::new(status, &status_text, &body_text);
-        resp.error = Some(self);
+        resp.error = Some(err);
         resp
     }
 }
 
+/// Record the redirects followed on the way to the response.
+///
+/// *Internal API*
+pub(crate) fn set_redirects(resp: &mut Response, redirects: Vec<RedirectStep>) {
+    resp.redirects = redirects;
+}
+
 /// "Give away" Unit and Stream to the response.
 ///
 /// *Internal API*
@@ -528,7 +1116,68 @@ pub(crate) fn set_stream(resp: &mut Response, url: String, unit: Option<Unit>, s
     resp.stream = Some(stream);
 }
 
-fn read_next_line<R: Read>(reader: &mut R) -> IoResult<String> {
+/// Close the connection of the response, leaving it with an empty body.
+///
+/// *Internal API*
+pub(crate) fn discard_body(resp: &mut Response) {
+    resp.unit = None;
+    resp.stream = None;
+}
+
+/// Read the server's answer to an `Expect: 100-continue`.
+///
+/// *Internal API*
+///
+/// `None` means we are to go ahead with the body, either because the server
+/// said `100 Continue` or because it didn't say anything before the read timed out.
+/// Other interim responses are skipped, any other status is the final response.
+pub(crate) fn read_interim<R: Read>(
+    mut reader: R,
+    opts: ParseOptions,
+) -> Result<Option<Response>, Error> {
+    loop {
+        let status_line = match read_next_line(&mut reader, opts) {
+            Ok(line) => line,
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                return Ok(None)
+            }
+            Err(e) if e.kind() == ErrorKind::ConnectionAborted => return Err(Error::BadStatusRead),
+            Err(_) => return Err(Error::BadStatus),
+        };
+        // put back the status line for the regular parsing.
+        let line = Cursor::new(format!("{}\r\n", status_line).into_bytes());
+        let resp = Response::do_from_read_with(line.chain(&mut reader), opts)?;
+        if resp.status() == 100 {
+            return Ok(None);
+        } else if !is_interim(resp.status()) {
+            return Ok(Some(resp));
+        }
+    }
+}
+
+/// Read the response, skipping any interim responses like `103 Early Hints`
+/// that come before it.
+///
+/// *Internal API*
+pub(crate) fn read_final<R: Read>(mut reader: R, opts: ParseOptions) -> Response {
+    loop {
+        let resp = Response::from_read_with(&mut reader, opts);
+        if resp.synthetic_error().is_some() || !is_interim(resp.status()) {
+            return resp;
+        }
+    }
+}
+
+/// 1xx responses are followed by the final response, except for `101 Switching
+/// Protocols` after which the connection is no longer HTTP.
+fn is_interim(status: u16) -> bool {
+    (100..200).contains(&status) && status != 101
+}
+
+// Reading byte by byte is deliberate, we must not consume anything past the
+// header lines since the rest of the stream is the body.
+#[allow(clippy::unbuffered_bytes)]
+fn read_next_line<R: Read>(reader: &mut R, opts: ParseOptions) -> IoResult<String> {
     let mut buf = Vec::new();
     let mut prev_byte_was_cr = false;
 
@@ -540,18 +1189,61 @@ fn read_next_line<R: Read>(reader: &mut R) -> IoResult<String> {
             None => return Err(IoError::new(ErrorKind::ConnectionAborted, "Unexpected EOF")),
         };
 
-        if byte == b'\n' && prev_byte_was_cr {
-            buf.pop(); // removing the '\r'
+        if byte == b'\n' {
+            if prev_byte_was_cr {
+                buf.pop(); // removing the '\r'
+            } else if !opts.lenient_line_endings {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Header line ends with a bare \\n",
+                ));
+            }
+            if buf.len() > opts.max_line_length {
+                return Err(line_too_long());
+            }
             return String::from_utf8(buf)
                 .map_err(|_| IoError::new(ErrorKind::InvalidInput, "Header is not in ASCII"));
         }
 
+        // a '\r' in the buffer might still turn out to be the line ending, so
+        // this only bounds the read. the exact length is checked at the end.
+        if buf.len() > opts.max_line_length {
+            return Err(line_too_long());
+        }
+
         prev_byte_was_cr = byte == b'\r';
 
         buf.push(byte);
     }
 }
 
+fn line_too_long() -> IoError {
+    IoError::new(ErrorKind::InvalidData, "Header line is too long")
+}
+
+/// Options for parsing the status line and headers of a response.
+///
+/// *Internal API*
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ParseOptions {
+    /// Accept header lines that end with a bare `\n` rather than `\r\n`.
+    pub lenient_line_endings: bool,
+    /// The longest status or header line we read, without the line ending.
+    pub max_line_length: usize,
+}
+
+/// The longest status or header line read unless the request says otherwise.
+pub(crate) const DEFAULT_MAX_LINE_LENGTH: usize = 8192;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            lenient_line_endings: false,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+        }
+    }
+}
+
 /// Read Wrapper around an (unsafe) pointer to a Stream.
 ///
 /// *Internal API*
@@ -595,14 +1287,16 @@ impl Drop for ReclaimingRead {
 }
 
 /// Limits a ReclaimingRead to a content size (as set by a "Content-Length" header).
+///
+/// The size is a u64, since bodies can be larger than a usize on 32 bit targets.
 struct LimitedRead {
     reader: ReclaimingRead,
-    limit: usize,
-    position: usize,
+    limit: u64,
+    position: u64,
 }
 
 impl LimitedRead {
-    fn new(reader: ReclaimingRead, limit: usize) -> Self {
+    fn new(reader: ReclaimingRead, limit: u64) -> Self {
         LimitedRead {
             reader,
             limit,
@@ -611,20 +1305,40 @@ impl LimitedRead {
     }
 }
 
+impl BodyRead for LimitedRead {
+    fn remaining(&self) -> Option<u64> {
+        Some(self.limit - self.position)
+    }
+}
+
+// the size of what is left of a chunked body isn't known.
+impl BodyRead for ChunkDecoder<ReclaimingRead> {}
+
 impl Read for LimitedRead {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
         let left = self.limit - self.position;
         if left == 0 {
             return Ok(0);
         }
-        let from = if left < buf.len() {
-            &mut buf[0..left]
+        // never read past the limit, whatever follows on a pooled stream
+        // belongs to the next response.
+        let from = if left < buf.len() as u64 {
+            &mut buf[0..left as usize]
         } else {
             buf
         };
+        let asked = from.len();
         match self.reader.read(from) {
+            // the server closed the connection before sending the whole body.
+            Ok(0) if asked > 0 => Err(IoError::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "Response body ended after {} of {} bytes",
+                    self.position, self.limit
+                ),
+            )),
             Ok(amount) => {
-                self.position += amount;
+                self.position += amount as u64;
                 Ok(amount)
             }
             Err(e) => Err(e),
@@ -632,6 +1346,180 @@ impl Read for LimitedRead {
     }
 }
 
+/// Parses server-sent events from the lines of a body.
+struct EventIter<I> {
+    lines: I,
+    last_id: Option<String>,
+}
+
+impl<I: Iterator<Item = IoResult<String>>> Iterator for EventIter<I> {
+    type Item = IoResult<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut event = None;
+        let mut data: Option<String> = None;
+        for line in &mut self.lines {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            // a blank line dispatches the event.
+            if line.is_empty() {
+                if let Some(mut data) = data.take() {
+                    data.pop(); // the last '\n'
+                    return Some(Ok(Event {
+                        id: self.last_id.clone(),
+                        event: event.unwrap_or_else(|| "message".to_string()),
+                        data,
+                    }));
+                }
+                event = None;
+                continue;
+            }
+            let (field, value) = match line.find(':') {
+                // a comment
+                Some(0) => continue,
+                Some(index) => {
+                    let value = &line[index + 1..];
+                    (&line[..index], value.strip_prefix(' ').unwrap_or(value))
+                }
+                None => (&line[..], ""),
+            };
+            match field {
+                "event" => event = Some(value.to_string()),
+                "data" => {
+                    let data = data.get_or_insert_with(String::new);
+                    data.push_str(value);
+                    data.push('\n');
+                }
+                "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+                _ => (), // includes retry, which is for reconnecting clients.
+            }
+        }
+        // an event that isn't finished by a blank line is never dispatched.
+        None
+    }
+}
+
+/// Reports the progress of reading a body to a callback.
+struct ProgressRead<R, F> {
+    reader: R,
+    progress: F,
+    read: u64,
+    total: Option<u64>,
+}
+
+impl<R: Read, F: FnMut(u64, Option<u64>)> Read for ProgressRead<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let amount = self.reader.read(buf)?;
+        if amount > 0 {
+            self.read += amount as u64;
+            (self.progress)(self.read, self.total);
+        }
+        Ok(amount)
+    }
+}
+
+/// Undo the transfer or content codings of a body, innermost (last listed)
+/// first. We stop at the first coding we can't decode, and leave the body as
+/// is from there on.
+///
+/// `gzip` and `deflate` require the `gzip` feature.
+fn decode_codings(mut reader: Box<dyn Read>, codings: &[String]) -> Box<dyn Read> {
+    for coding in codings.iter().rev() {
+        reader = match coding.as_str() {
+            // dealt with by the framing.
+            "chunked" | "identity" => reader,
+            #[cfg(feature = "gzip")]
+            "gzip" | "x-gzip" => Box::new(DecodeToEnd(GzDecoder::new(reader))),
+            #[cfg(feature = "gzip")]
+            "deflate" => Box::new(DecodeToEnd(ZlibDecoder::new(reader))),
+            _ => return reader,
+        };
+    }
+    reader
+}
+
+/// A decoder over the framed body.
+#[cfg(feature = "gzip")]
+trait Decoder: Read {
+    fn inner(&mut self) -> &mut Box<dyn Read>;
+}
+
+#[cfg(feature = "gzip")]
+impl Decoder for GzDecoder<Box<dyn Read>> {
+    fn inner(&mut self) -> &mut Box<dyn Read> {
+        self.get_mut()
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl Decoder for ZlibDecoder<Box<dyn Read>> {
+    fn inner(&mut self) -> &mut Box<dyn Read> {
+        self.get_mut()
+    }
+}
+
+/// Reader that, once the compressed stream ends, reads the framed body to its
+/// end as well.
+///
+/// A decoder stops at the end of the compressed data, which can leave the last
+/// chunk or bytes past the trailer unread. The connection only goes back to the
+/// pool when the framing reader has been read to the end.
+#[cfg(feature = "gzip")]
+struct DecodeToEnd<D>(D);
+
+#[cfg(feature = "gzip")]
+impl<D: Decoder> Read for DecodeToEnd<D> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let amount = self.0.read(buf)?;
+        if amount == 0 && !buf.is_empty() {
+            io::copy(self.0.inner(), &mut io::sink())?;
+        }
+        Ok(amount)
+    }
+}
+
+#[cfg(feature = "json")]
+fn read_json(reader: impl Read) -> IoResult<serde_json::Value> {
+    serde_json::from_reader(reader).map_err(|e| {
+        IoError::new(
+            ErrorKind::InvalidData,
+            format!("Failed to read JSON: {}", e),
+        )
+    })
+}
+
+/// Reader that errors instead of reading more than `limit` bytes.
+#[cfg(feature = "json")]
+struct SizeGuard<R> {
+    reader: R,
+    limit: usize,
+    left: usize,
+}
+
+#[cfg(feature = "json")]
+impl<R: Read> Read for SizeGuard<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        // one byte past what is left tells us whether the body is too large.
+        let max = buf.len().min(self.left + 1);
+        let amount = self.reader.read(&mut buf[..max])?;
+        if amount > self.left {
+            return Err(body_too_large(self.limit));
+        }
+        self.left -= amount;
+        Ok(amount)
+    }
+}
+
+#[cfg(feature = "json")]
+fn body_too_large(limit: usize) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidData,
+        format!("Response body is larger than the limit of {} bytes", limit),
+    )
+}
+
 /// Extract the charset from a "Content-Type" header.
 ///
 /// "Content-Type: text/plain; charset=iso8859-1" -> "iso8859-1"
@@ -640,12 +1528,21 @@ impl Read for LimitedRead {
 pub(crate) fn charset_from_content_type(header: Option<&str>) -> &str {
     header
         .and_then(|header| {
-            header.find(';').and_then(|semi| {
-                (&header[semi + 1..])
-                    .find('=')
-                    .map(|equal| (&header[semi + equal + 2..]).trim())
+            // the first part is the mime type, then parameters "name=value"
+            header.split(';').skip(1).find_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                if !name.trim().eq_ignore_ascii_case("charset") {
+                    return None;
+                }
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                Some(value)
             })
         })
+        .filter(|charset| !charset.is_empty())
         .unwrap_or(DEFAULT_CHARACTER_SET)
 }
 
@@ -653,6 +1550,52 @@ pub(crate) fn charset_from_content_type(header: Option<&str>) -> &str {
 mod tests {
     use super::*;
 
+    #[test]
+    fn content_length_beyond_u32() {
+        let s = "HTTP/1.1 200 OK\r\n\
+                 Content-Length: 5000000000\r\n\
+                 \r\n\
+                 abc";
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!(
+            header::content_length(&resp.headers),
+            Ok(Some(5_000_000_000))
+        );
+
+        let mut reader = resp.into_reader();
+        let mut buf = [0_u8; 10];
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        let err = reader.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert!(err.to_string().contains("3 of 5000000000 bytes"));
+    }
+
+    #[test]
+    fn status_parts() {
+        let s = "HTTP/1.1 301 Moved\r\n\r\n";
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!(resp.status_parts(), (Version::Http11, 301, "Moved"));
+
+        let s = "HTTP/1.0 404 Not Found\r\n\r\n";
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!(resp.status_parts(), (Version::Http10, 404, "Not Found"));
+    }
+
+    #[test]
+    fn version_mapping() {
+        assert_eq!(Version::from_status_line("HTTP/1.0"), Version::Http10);
+        assert_eq!(Version::from_status_line("HTTP/1.1"), Version::Http11);
+        assert_eq!(Version::from_status_line("http/1.1"), Version::Http11);
+        assert_eq!(Version::from_status_line("HTTP/2"), Version::Http2);
+        assert_eq!(Version::from_status_line("HTTP/2.0"), Version::Http2);
+        assert_eq!(Version::from_status_line("HTTP/3"), Version::Unknown);
+
+        let s = "ICY/9.9 200 OK\r\n\r\n";
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!(resp.status_parts(), (Version::Unknown, 200, "OK"));
+        assert_eq!(resp.http_version(), "ICY/9.9");
+    }
+
     #[test]
     fn content_type_without_charset() {
         let s = "HTTP/1.1 200 OK\r\n\
@@ -690,6 +1633,36 @@ mod tests {
         assert_eq!("iso-8859-4", resp.charset());
     }
 
+    #[test]
+    fn charset_among_parameters() {
+        let s = "HTTP/1.1 200 OK\r\n\
+                 Content-Type: multipart/mixed; boundary=x; Charset=utf-8; foo=bar\r\n\
+                 \r\n\
+                 OK";
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!("utf-8", resp.charset());
+    }
+
+    #[test]
+    fn charset_quoted() {
+        let s = "HTTP/1.1 200 OK\r\n\
+                 Content-Type: text/html; charset=\"iso-8859-4\"\r\n\
+                 \r\n\
+                 OK";
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!("iso-8859-4", resp.charset());
+    }
+
+    #[test]
+    fn charset_not_first_equals() {
+        let s = "HTTP/1.1 200 OK\r\n\
+                 Content-Type: text/plain; format=flowed\r\n\
+                 \r\n\
+                 OK";
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!("utf-8", resp.charset());
+    }
+
     #[test]
     fn charset_default() {
         let s = "HTTP/1.1 200 OK\r\n\
@@ -718,11 +1691,9 @@ mod tests {
     #[test]
     #[cfg(feature = "json")]
     fn parse_simple_json() {
-        let s = format!(
-            "HTTP/1.1 200 OK\r\n\
-             \r\n\
-             {{\"hello\":\"world\"}}"
-        );
+        let s = "HTTP/1.1 200 OK\r\n\
+                 \r\n\
+                 {\"hello\":\"world\"}";
         let resp = s.parse::<Response>().unwrap();
         let v = resp.into_json().unwrap();
         let compare = "{\"hello\":\"world\"}"
@@ -731,6 +1702,113 @@ mod tests {
         assert_eq!(v, compare);
     }
 
+    #[test]
+    #[cfg(feature = "json")]
+    fn json_within_limit() {
+        let s = "HTTP/1.1 200 OK\r\n\
+                 Content-Length: 17\r\n\
+                 \r\n\
+                 {\"hello\":\"world\"}";
+        let resp = s.parse::<Response>().unwrap();
+        let v = resp.into_json_with_limit(17).unwrap();
+        assert_eq!(v["hello"], "world");
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json_over_limit() {
+        // without a content-length, the limit hits while reading.
+        let s = "HTTP/1.1 200 OK\r\n\
+                 \r\n\
+                 {\"hello\":\"world\", \"padding\": \"xxxxxxxxxxxxxxxxxxxxxxxx\"}";
+        let resp = s.parse::<Response>().unwrap();
+        let err = resp.into_json_with_limit(20).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err
+            .to_string()
+            .contains("larger than the limit of 20 bytes"));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json_over_limit_content_length() {
+        let s = "HTTP/1.1 200 OK\r\n\
+                 Content-Length: 17\r\n\
+                 \r\n\
+                 {\"hello\":\"world\"}";
+        let resp = s.parse::<Response>().unwrap();
+        let err = resp.into_json_with_limit(16).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Response body is larger than the limit of 16 bytes"
+        );
+    }
+
+    #[test]
+    fn connection_reuse_by_version() {
+        let reusable = |s: &str| s.parse::<Response>().unwrap().is_connection_reusable();
+        assert!(reusable("HTTP/1.1 200 OK\r\n\r\n"));
+        assert!(!reusable("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n"));
+        assert!(!reusable("HTTP/1.0 200 OK\r\n\r\n"));
+        assert!(reusable(
+            "HTTP/1.0 200 OK\r\nConnection: keep-alive\r\n\r\n"
+        ));
+        assert!(reusable(
+            "HTTP/1.0 200 OK\r\nConnection: Keep-Alive, foo\r\n\r\n"
+        ));
+    }
+
+    #[test]
+    fn http10_keep_alive_limits_body() {
+        // the body must be limited for the connection to be reused.
+        let s = "HTTP/1.0 200 OK\r\n\
+                 Connection: keep-alive\r\n\
+                 Content-Length: 5\r\n\
+                 \r\n\
+                 HelloHTTP/1.0 200 OK";
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!(resp.into_string().unwrap(), "Hello");
+    }
+
+    #[test]
+    fn parse_folded_header() {
+        let s = "HTTP/1.1 200 OK\r\n\
+                 X-Folded: first part\r\n\
+                 \t  second part\r\n\
+                 X-Other: value\r\n\
+                 \r\n"
+            .to_string();
+        let resp = s.parse::<Response>().unwrap();
+        assert_eq!(resp.header("x-folded"), Some("first part second part"));
+        assert_eq!(resp.header("x-other"), Some("value"));
+        assert_eq!(resp.headers_names().len(), 2);
+    }
+
+    #[test]
+    fn try_clone() {
+        let s = "HTTP/1.1 203 Non-Authoritative Information\r\n\
+                 X-Foo: bar\r\n\
+                 \r\n\
+                 Hello"
+            .to_string();
+        let resp = s.parse::<Response>().unwrap();
+        let copy = resp.try_clone().unwrap();
+        assert_eq!(copy.status_line(), resp.status_line());
+        assert_eq!(copy.status(), 203);
+        assert_eq!(copy.header("x-foo"), Some("bar"));
+        assert_eq!(copy.into_string().unwrap(), "Hello");
+        assert_eq!(resp.into_string().unwrap(), "Hello");
+    }
+
+    #[test]
+    fn try_clone_synthetic() {
+        let resp: Response = Error::BadStatus.into();
+        let copy = resp.try_clone().unwrap();
+        assert!(copy.synthetic());
+        assert_eq!(copy.status(), 500);
+        assert_eq!(copy.into_string().unwrap(), "Bad Status\n");
+    }
+
     #[test]
     fn parse_borked_header() {
         let s = "HTTP/1.1 BORKED\r\n".to_string();
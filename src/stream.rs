@@ -2,18 +2,24 @@ use std::io::{Cursor, Read, Result as IoResult, Write};
 use std::net::SocketAddr;
 use std::net::TcpStream;
 use std::net::ToSocketAddrs;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tls")]
+use std::io::ErrorKind;
 
 #[cfg(feature = "tls")]
 use rustls::ClientSession;
 #[cfg(feature = "tls")]
+use rustls::Session;
+#[cfg(feature = "tls")]
 use rustls::StreamOwned;
 
 use crate::error::Error;
 use crate::unit::Unit;
 
 #[allow(clippy::large_enum_variant)]
-pub enum Stream {
+pub(crate) enum Stream {
     Http(TcpStream),
     #[cfg(feature = "tls")]
     Https(rustls::StreamOwned<rustls::ClientSession, TcpStream>),
@@ -39,8 +45,91 @@ impl ::std::fmt::Debug for Stream {
     }
 }
 
+/// The connection to a server, plain or over TLS, taken from a response.
+///
+/// See [`response.into_stream()`](struct.Response.html#method.into_stream).
+pub struct Connection(Stream);
+
+impl Connection {
+    pub(crate) fn new(stream: Stream) -> Self {
+        Connection(stream)
+    }
+}
+
+impl ::std::fmt::Debug for Connection {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::result::Result<(), ::std::fmt::Error> {
+        write!(f, "Connection[{:?}]", self.0)
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0.write(buf)
+    }
+    fn flush(&mut self) -> IoResult<()> {
+        self.0.flush()
+    }
+}
+
+/// The TLS version and cipher suite negotiated for an https connection.
+///
+/// See [`response.tls_info()`](struct.Response.html#method.tls_info).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsInfo {
+    version: String,
+    cipher_suite: String,
+}
+
+impl TlsInfo {
+    /// The protocol version, like `TLSv1.3`.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The IANA name of the cipher suite, like `TLS13_AES_256_GCM_SHA384`.
+    pub fn cipher_suite(&self) -> &str {
+        &self.cipher_suite
+    }
+}
+
 impl Stream {
-    pub fn is_poolable(&self) -> bool {
+    /// The negotiated TLS parameters, once the handshake is done.
+    pub(crate) fn tls_info(&self) -> Option<TlsInfo> {
+        match self {
+            #[cfg(feature = "tls")]
+            Stream::Https(stream) => {
+                let version = match stream.sess.get_protocol_version()? {
+                    rustls::ProtocolVersion::TLSv1_2 => "TLSv1.2".to_string(),
+                    rustls::ProtocolVersion::TLSv1_3 => "TLSv1.3".to_string(),
+                    v => format!("{:?}", v),
+                };
+                let suite = stream.sess.get_negotiated_ciphersuite()?;
+                Some(TlsInfo {
+                    version,
+                    cipher_suite: format!("{:?}", suite.suite),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The address of the server at the other end of the connection.
+    pub(crate) fn remote_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Stream::Http(sock) => sock.peer_addr().ok(),
+            #[cfg(feature = "tls")]
+            Stream::Https(stream) => stream.sock.peer_addr().ok(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_poolable(&self) -> bool {
         match self {
             Stream::Http(_) => true,
             #[cfg(feature = "tls")]
@@ -49,8 +138,28 @@ impl Stream {
         }
     }
 
+    /// Pooled streams are reused by units that might have other timeouts
+    /// than the one that opened the connection.
+    pub(crate) fn set_unit_timeouts(&self, unit: &Unit) {
+        match self {
+            Stream::Http(sock) => set_socket_timeouts(sock, unit),
+            #[cfg(feature = "tls")]
+            Stream::Https(stream) => set_socket_timeouts(&stream.sock, unit),
+            _ => (),
+        }
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) {
+        match self {
+            Stream::Http(sock) => sock.set_read_timeout(timeout).ok(),
+            #[cfg(feature = "tls")]
+            Stream::Https(stream) => stream.sock.set_read_timeout(timeout).ok(),
+            _ => None,
+        };
+    }
+
     #[cfg(test)]
-    pub fn to_write_vec(&self) -> Vec<u8> {
+    pub(crate) fn to_write_vec(&self) -> Vec<u8> {
         match self {
             Stream::Test(_, writer) => writer.clone(),
             _ => panic!("to_write_vec on non Test stream"),
@@ -91,7 +200,7 @@ fn is_close_notify(e: &std::io::Error) -> bool {
     if let Some(msg) = e.get_ref() {
         // :(
 
-        return msg.description().contains("CloseNotify");
+        return msg.to_string().contains("CloseNotify");
     }
 
     false
@@ -147,15 +256,64 @@ pub(crate) fn connect_https(unit: &Unit) -> Result<Stream, Error> {
     let port = unit.url.port().unwrap_or(443);
 
     let sni = webpki::DNSNameRef::try_from_ascii_str(hostname).unwrap();
-    let sess = rustls::ClientSession::new(&*TLS_CONF, sni);
+    let mut sess = rustls::ClientSession::new(&TLS_CONF, sni);
 
-    let sock = connect_host(unit, hostname, port)?;
+    let start = Instant::now();
+    let mut sock = connect_host(unit, hostname, port)?;
+
+    // without a connect timeout, the handshake happens lazily on first write.
+    if unit.timeout_connect > 0 {
+        let deadline = start + Duration::from_millis(unit.timeout_connect);
+        tls_handshake(&mut sess, &mut sock, deadline)?;
+        set_socket_timeouts(&sock, unit);
+    }
 
     let stream = rustls::StreamOwned::new(sess, sock);
 
     Ok(Stream::Https(stream))
 }
 
+/// Drive the TLS handshake to completion, failing if it isn't done by the deadline.
+///
+/// The socket timeouts are changed to bound each read/write to the time that is left.
+#[cfg(feature = "tls")]
+fn tls_handshake(
+    sess: &mut ClientSession,
+    sock: &mut TcpStream,
+    deadline: Instant,
+) -> Result<(), Error> {
+    let timed_out = || Error::ConnectionFailed("TLS handshake timed out".to_string());
+    let failed = |err: std::io::Error| match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => timed_out(),
+        _ => Error::ConnectionFailed(format!("TLS handshake failed: {}", err)),
+    };
+
+    while sess.is_handshaking() {
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(timed_out());
+        }
+        let left = deadline - now;
+        sock.set_read_timeout(Some(left)).ok();
+        sock.set_write_timeout(Some(left)).ok();
+
+        while sess.wants_write() {
+            sess.write_tls(sock).map_err(failed)?;
+        }
+        if sess.is_handshaking() && sess.wants_read() {
+            if sess.read_tls(sock).map_err(failed)? == 0 {
+                return Err(Error::ConnectionFailed(
+                    "TLS handshake failed: unexpected EOF".to_string(),
+                ));
+            }
+            sess.process_new_packets()
+                .map_err(|e| Error::ConnectionFailed(format!("TLS handshake failed: {}", e)))?;
+        }
+    }
+
+    Ok(())
+}
+
 pub(crate) fn connect_host(unit: &Unit, hostname: &str, port: u16) -> Result<TcpStream, Error> {
     //
     let ips: Vec<SocketAddr> = format!("{}:{}", hostname, port)
@@ -170,39 +328,66 @@ pub(crate) fn connect_host(unit: &Unit, hostname: &str, port: u16) -> Result<Tcp
     // pick first ip, or should we randomize?
     let sock_addr = ips[0];
 
-    // connect with a configured timeout.
-    let stream = match unit.timeout_connect {
-        0 => TcpStream::connect(&sock_addr),
-        _ => TcpStream::connect_timeout(
-            &sock_addr,
-            Duration::from_millis(unit.timeout_connect as u64),
-        ),
+    let stream = connect_with_retries(unit, sock_addr)
+        .map_err(|err| Error::ConnectionFailed(format!("{}", err)))?;
+
+    set_socket_timeouts(&stream, unit);
+
+    Ok(stream)
+}
+
+/// The longest pause between connect retries that doubling the backoff leads to.
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Connect with the configured timeout, retrying failed attempts according to the unit.
+fn connect_with_retries(unit: &Unit, sock_addr: SocketAddr) -> IoResult<TcpStream> {
+    let deadline = match unit.timeout_connect {
+        0 => None,
+        millis => Some(Instant::now() + Duration::from_millis(millis)),
+    };
+    let mut backoff = Duration::from_millis(unit.connect_backoff);
+    // the doubling stops at the cap, unless the caller asked for more to begin with.
+    let max_backoff = MAX_CONNECT_BACKOFF.max(backoff);
+    let mut retries = unit.connect_retries;
+
+    loop {
+        let result = match deadline {
+            None => TcpStream::connect(sock_addr),
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                // connect_timeout refuses a zero duration.
+                TcpStream::connect_timeout(&sock_addr, left.max(Duration::from_millis(1)))
+            }
+        };
+        let err = match result {
+            Ok(stream) => return Ok(stream),
+            Err(err) => err,
+        };
+        let out_of_time = deadline
+            .map(|d| Instant::now() + backoff >= d)
+            .unwrap_or(false);
+        if retries == 0 || out_of_time {
+            return Err(err);
+        }
+        retries -= 1;
+        thread::sleep(backoff);
+        backoff = backoff
+            .checked_mul(2)
+            .unwrap_or(max_backoff)
+            .min(max_backoff);
     }
-    .map_err(|err| Error::ConnectionFailed(format!("{}", err)))?;
+}
 
+/// Apply the read/write timeouts of the unit to a socket.
+fn set_socket_timeouts(sock: &TcpStream, unit: &Unit) {
     // rust's absurd api returns Err if we set 0.
     // Setting it to None will disable the native system timeout
-    if unit.timeout_read > 0 {
-        stream
-            .set_read_timeout(Some(Duration::from_millis(unit.timeout_read as u64)))
-            .ok();
-    } else {
-        stream
-            .set_read_timeout(None)
-            .ok();
-    }
-
-    if unit.timeout_write > 0 {
-        stream
-            .set_write_timeout(Some(Duration::from_millis(unit.timeout_write as u64)))
-            .ok();
-    } else {
-        stream
-            .set_write_timeout(None)
-            .ok();
-    }
-
-    Ok(stream)
+    let to_duration = |millis: u64| match millis {
+        0 => None,
+        _ => Some(Duration::from_millis(millis)),
+    };
+    sock.set_read_timeout(to_duration(unit.timeout_read)).ok();
+    sock.set_write_timeout(to_duration(unit.timeout_write)).ok();
 }
 
 #[cfg(test)]
@@ -53,6 +53,120 @@ fn agent_cookies() {
     agent.get("test://host/agent_cookies").call();
 }
 
+#[cfg(feature = "cookie")]
+#[test]
+fn agent_set_cookie() {
+    let agent = agent();
+
+    let cookie = Cookie::build("session", "abc123")
+        .domain("host")
+        .path("/")
+        .finish();
+    agent.set_cookie(cookie);
+    // never sent, the domain doesn't match.
+    agent.set_cookie(Cookie::build("other", "xyz").domain("elsewhere").finish());
+
+    let mut names: Vec<_> = agent
+        .cookies()
+        .iter()
+        .map(|c| c.name().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["other", "session"]);
+
+    test::set_handler("/agent_set_cookie", |unit| {
+        assert_eq!(unit.all("cookie"), vec!["session=abc123"]);
+        test::make_response(200, "OK", vec![], vec![])
+    });
+
+    agent.get("test://host/agent_set_cookie").call();
+}
+
+#[cfg(feature = "cookie")]
+#[test]
+fn agent_no_cookies() {
+    let agent = agent();
+    agent.set_cookie(Cookie::build("session", "abc123").domain("host").finish());
+
+    test::set_handler("/agent_no_cookies", |unit| {
+        assert!(!unit.has("cookie"));
+        test::make_response(200, "OK", vec!["Set-Cookie: foo=bar"], vec![])
+    });
+
+    let resp = agent
+        .get("test://host/agent_no_cookies")
+        .no_cookies()
+        .call();
+    assert_eq!(resp.status(), 200);
+    assert!(agent.cookie("foo").is_none());
+    assert!(agent.cookie("session").is_some());
+}
+
+#[cfg(feature = "cookie")]
+#[test]
+fn agent_cookie_prefixes() {
+    let agent = agent();
+
+    test::set_handler("/agent_cookie_prefixes", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec![
+                "Set-Cookie: __Host-id=1; Secure; Path=/; Domain=host",
+                "Set-Cookie: __Secure-id=2; Secure",
+                "Set-Cookie: plain=3",
+            ],
+            vec![],
+        )
+    });
+
+    agent.get("test://host/agent_cookie_prefixes").call();
+    let names: Vec<_> = agent
+        .cookies()
+        .iter()
+        .map(|c| c.name().to_string())
+        .collect();
+    assert_eq!(names, vec!["plain"]);
+}
+
+#[test]
+fn agent_default_content_type() {
+    let agent = agent().default_content_type("application/json").build();
+
+    test::set_handler("/agent_default_content_type", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = agent.get("test://host/agent_default_content_type").call();
+    assert_eq!(resp.content_type(), "application/json");
+
+    test::set_handler("/agent_default_content_type", |_unit| {
+        test::make_response(200, "OK", vec!["Content-Type: text/html"], vec![])
+    });
+    let resp = agent.get("test://host/agent_default_content_type").call();
+    assert_eq!(resp.content_type(), "text/html");
+}
+
+#[test]
+fn host_header_override() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        let head = test::read_request_head(&mut stream);
+        assert!(head.contains("\r\nHost: example.com\r\n"), "{}", head);
+        assert!(!head.contains("127.0.0.1"));
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nSet-Cookie: id=1\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    let agent = agent();
+    let resp = agent
+        .get(&format!("http://127.0.0.1:{}/", port))
+        .host_header("example.com")
+        .call();
+    assert_eq!(resp.status(), 200);
+    #[cfg(feature = "cookie")]
+    assert_eq!(agent.cookie("id").unwrap().domain(), Some("example.com"));
+}
+
 #[test]
 #[cfg(feature = "tls")]
 fn connection_reuse() {
@@ -79,3 +193,117 @@ fn connection_reuse() {
     }
     assert_eq!(resp.status(), 200);
 }
+
+#[test]
+fn pipelined_responses() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        // both responses are on the wire before the client has read the first.
+        stream
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nfirst\
+                  HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond",
+            )
+            .unwrap();
+        test::read_request_head(&mut stream);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    });
+    let agent = agent();
+    let url = format!("http://127.0.0.1:{}/", port);
+
+    let resp = agent.get(&url).call();
+    assert_eq!(resp.into_string().unwrap(), "first");
+
+    // the connection went back to the pool with the second response unread.
+    let resp = agent.get(&url).call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.into_string().unwrap(), "second");
+}
+
+fn pooled_connections(agent: &Agent) -> usize {
+    agent.state.lock().unwrap().as_mut().unwrap().pool().len()
+}
+
+fn unread_body_server(len: usize) -> u16 {
+    test::run_server(move |mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", len);
+        stream.write_all(head.as_bytes()).unwrap();
+        stream.write_all(&vec![b'x'; len]).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    })
+}
+
+#[test]
+fn unread_small_body_is_drained() {
+    let agent = agent().drain_limit(1000).build();
+    let port = unread_body_server(500);
+    let resp = agent.get(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    drop(resp.into_reader());
+    assert_eq!(pooled_connections(&agent), 1);
+}
+
+#[test]
+fn unread_large_body_is_not_read() {
+    let agent = agent().drain_limit(1000).build();
+    // the server never sends the body, reading it would wait for the drain timeout.
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100000\r\n\r\n")
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(2));
+    });
+    let resp = agent.get(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    let start = std::time::Instant::now();
+    drop(resp.into_reader());
+    assert!(start.elapsed() < std::time::Duration::from_millis(500));
+    assert_eq!(pooled_connections(&agent), 0);
+}
+
+#[test]
+fn unread_large_body_closes_connection() {
+    let agent = agent().drain_limit(1000).build();
+    let port = unread_body_server(100_000);
+    let resp = agent.get(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    drop(resp.into_reader());
+    assert_eq!(pooled_connections(&agent), 0);
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip_body_read_to_framing_end() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Read, Write};
+
+    let mut enc = GzEncoder::new(vec![], Compression::default());
+    enc.write_all(b"hello world").unwrap();
+    let gzipped = enc.finish().unwrap();
+    let port = test::run_server(move |mut stream| {
+        test::read_request_head(&mut stream);
+        let head = "HTTP/1.1 200 OK\r\n\
+                    Transfer-Encoding: chunked\r\n\
+                    Content-Encoding: gzip\r\n\r\n";
+        stream.write_all(head.as_bytes()).unwrap();
+        write!(stream, "{:x}\r\n", gzipped.len()).unwrap();
+        stream.write_all(&gzipped).unwrap();
+        stream.write_all(b"\r\n0\r\n\r\n").unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    });
+    let agent = agent().build();
+    let resp = agent.get(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    let mut reader = resp.into_reader();
+    let mut body = String::new();
+    reader.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "hello world");
+    // the last chunk was read with the body, not when the reader drops.
+    assert_eq!(pooled_connections(&agent), 1);
+}
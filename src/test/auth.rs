@@ -57,3 +57,96 @@ fn url_auth_overridden() {
         .call();
     assert_eq!(resp.status(), 200);
 }
+
+#[test]
+fn basic_challenge() {
+    test::set_handler("/basic_challenge", |_unit| {
+        test::make_response(
+            401,
+            "Unauthorized",
+            vec!["WWW-Authenticate: Basic realm=\"Access to staging\", charset=\"UTF-8\""],
+            vec![],
+        )
+    });
+    let resp = get("test://host/basic_challenge").call();
+    let challenges = resp.auth_challenges();
+    assert_eq!(challenges.len(), 1);
+    assert!(challenges[0].is_scheme("basic"));
+    assert_eq!(challenges[0].param("realm"), Some("Access to staging"));
+    assert_eq!(challenges[0].param("Charset"), Some("UTF-8"));
+}
+
+#[test]
+fn digest_challenge() {
+    test::set_handler("/digest_challenge", |_unit| {
+        test::make_response(
+            401,
+            "Unauthorized",
+            vec![
+                "WWW-Authenticate: Digest realm=\"http-auth@example.org\", \
+                 qop=\"auth, auth-int\", algorithm=SHA-256, \
+                 nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                 opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+            ],
+            vec![],
+        )
+    });
+    let resp = get("test://host/digest_challenge").call();
+    let challenges = resp.auth_challenges();
+    assert_eq!(challenges.len(), 1);
+    let digest = &challenges[0];
+    assert_eq!(digest.scheme(), "Digest");
+    assert_eq!(digest.param("realm"), Some("http-auth@example.org"));
+    assert_eq!(digest.param("qop"), Some("auth, auth-int"));
+    assert_eq!(digest.param("algorithm"), Some("SHA-256"));
+    assert_eq!(
+        digest.param("nonce"),
+        Some("7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v")
+    );
+    assert_eq!(digest.params().len(), 5);
+}
+
+#[test]
+fn multiple_challenges() {
+    test::set_handler("/multiple_challenges", |_unit| {
+        test::make_response(
+            401,
+            "Unauthorized",
+            vec![
+                "WWW-Authenticate: Newauth realm=\"apps\", type=1, \
+                 title=\"Login to \\\"apps\\\"\", Basic realm=\"simple\"",
+                "WWW-Authenticate: Negotiate, Bearer dXNlcjpwYXNz==",
+            ],
+            vec![],
+        )
+    });
+    let resp = get("test://host/multiple_challenges").call();
+    let challenges = resp.auth_challenges();
+    let schemes: Vec<_> = challenges.iter().map(|c| c.scheme()).collect();
+    assert_eq!(schemes, vec!["Newauth", "Basic", "Negotiate", "Bearer"]);
+    assert_eq!(challenges[0].param("type"), Some("1"));
+    assert_eq!(challenges[0].param("title"), Some("Login to \"apps\""));
+    assert_eq!(challenges[1].param("realm"), Some("simple"));
+    assert!(challenges[2].params().is_empty());
+    assert_eq!(challenges[2].token68(), None);
+    assert_eq!(challenges[3].token68(), Some("dXNlcjpwYXNz=="));
+}
+
+#[test]
+fn unpadded_token68_challenge() {
+    test::set_handler("/unpadded_token68_challenge", |_unit| {
+        test::make_response(
+            401,
+            "Unauthorized",
+            vec!["WWW-Authenticate: Negotiate YII/ab+c, Basic realm=\"x\""],
+            vec![],
+        )
+    });
+    let resp = get("test://host/unpadded_token68_challenge").call();
+    let challenges = resp.auth_challenges();
+    assert_eq!(challenges.len(), 2);
+    assert!(challenges[0].is_scheme("negotiate"));
+    assert_eq!(challenges[0].token68(), Some("YII/ab+c"));
+    assert!(challenges[1].is_scheme("basic"));
+    assert_eq!(challenges[1].param("realm"), Some("x"));
+}
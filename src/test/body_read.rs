@@ -2,6 +2,7 @@ use crate::test;
 use std::io::Read;
 
 use super::super::*;
+use crate::stream::Stream;
 
 #[test]
 fn transfer_encoding_bogus() {
@@ -10,7 +11,7 @@ fn transfer_encoding_bogus() {
             200,
             "OK",
             vec![
-                "transfer-encoding: bogus", // whatever it says here, we should chunk
+                "transfer-encoding: bogus", // not chunked, so read until close
             ],
             "3\r\nhel\r\nb\r\nlo world!!!\r\n0\r\n\r\n"
                 .to_string()
@@ -21,7 +22,144 @@ fn transfer_encoding_bogus() {
     let mut reader = resp.into_reader();
     let mut text = String::new();
     reader.read_to_string(&mut text).unwrap();
-    assert_eq!(text, "hello world!!!");
+    assert_eq!(text, "3\r\nhel\r\nb\r\nlo world!!!\r\n0\r\n\r\n");
+}
+
+#[test]
+fn transfer_encoding_chunked() {
+    test::set_handler("/transfer_encoding_chunked", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Transfer-Encoding: Chunked"],
+            "3\r\nhel\r\nb\r\nlo world!!!\r\n0\r\n\r\n"
+                .to_string()
+                .into_bytes(),
+        )
+    });
+    let resp = get("test://host/transfer_encoding_chunked").call();
+    assert_eq!(resp.into_string().unwrap(), "hello world!!!");
+}
+
+#[test]
+fn transfer_encoding_identity() {
+    test::set_handler("/transfer_encoding_identity", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Length: 4", "Transfer-Encoding: identity"],
+            "hello world!!!".to_string().into_bytes(),
+        )
+    });
+    let resp = get("test://host/transfer_encoding_identity").call();
+    // the content-length doesn't apply with a transfer-encoding
+    assert_eq!(resp.into_string().unwrap(), "hello world!!!");
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn transfer_encoding_gzip_chunked() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut enc = GzEncoder::new(vec![], Compression::default());
+    enc.write_all(b"hello world!!!").unwrap();
+    let gzipped = enc.finish().unwrap();
+    let mut body = format!("{:x}\r\n", gzipped.len()).into_bytes();
+    body.extend_from_slice(&gzipped);
+    body.extend_from_slice(b"\r\n0\r\n\r\n");
+
+    test::set_handler("/transfer_encoding_gzip_chunked", move |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Transfer-Encoding: gzip, chunked"],
+            body.clone(),
+        )
+    });
+    let resp = get("test://host/transfer_encoding_gzip_chunked").call();
+    assert_eq!(resp.into_string().unwrap(), "hello world!!!");
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn content_encoding_gzip() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut enc = GzEncoder::new(vec![], Compression::default());
+    enc.write_all(b"hello world!!!").unwrap();
+    let gzipped = enc.finish().unwrap();
+    let len = format!("Content-Length: {}", gzipped.len());
+
+    test::set_handler("/content_encoding_gzip", move |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Encoding: gzip", &len],
+            gzipped.clone(),
+        )
+    });
+    let resp = get("test://host/content_encoding_gzip").call();
+    assert_eq!(resp.into_string().unwrap(), "hello world!!!");
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn content_encoding_raw_reader() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut enc = GzEncoder::new(vec![], Compression::default());
+    enc.write_all(b"hello world!!!").unwrap();
+    let gzipped = enc.finish().unwrap();
+    let len = format!("Content-Length: {}", gzipped.len());
+
+    let body = gzipped.clone();
+    test::set_handler("/content_encoding_raw_reader", move |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Encoding: gzip", &len],
+            body.clone(),
+        )
+    });
+    let resp = get("test://host/content_encoding_raw_reader").call();
+    let mut raw = vec![];
+    resp.into_raw_reader().read_to_end(&mut raw).unwrap();
+    assert_eq!(raw, gzipped);
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn content_encoding_deflate_empty() {
+    test::set_handler("/content_encoding_deflate_empty", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Encoding: deflate", "Content-Length: 0"],
+            vec![],
+        )
+    });
+    let resp = get("test://host/content_encoding_deflate_empty").call();
+    assert_eq!(resp.into_string().unwrap(), "");
+}
+
+#[test]
+fn transfer_encoding_chunked_not_last() {
+    test::set_handler("/transfer_encoding_chunked_not_last", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Transfer-Encoding: chunked", "Transfer-Encoding: bogus"],
+            "3\r\nhel\r\n0\r\n\r\n".to_string().into_bytes(),
+        )
+    });
+    let resp = get("test://host/transfer_encoding_chunked_not_last").call();
+    assert_eq!(resp.into_string().unwrap(), "3\r\nhel\r\n0\r\n\r\n");
 }
 
 #[test]
@@ -80,3 +218,367 @@ fn no_reader_on_head() {
     reader.read_to_string(&mut text).unwrap();
     assert_eq!(text, "");
 }
+
+#[test]
+fn duplicate_equal_content_length() {
+    test::set_handler("/duplicate_equal_content_length", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Length: 4", "Content-Length: 4, 4"],
+            "abcdefgh".to_string().into_bytes(),
+        )
+    });
+    let resp = get("test://host/duplicate_equal_content_length").call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.into_string().unwrap(), "abcd");
+}
+
+#[test]
+fn duplicate_differing_content_length() {
+    test::set_handler("/duplicate_differing_content_length", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Length: 4", "Content-Length: 8"],
+            "abcdefgh".to_string().into_bytes(),
+        )
+    });
+    let resp = get("test://host/duplicate_differing_content_length").call();
+    assert!(matches!(resp.synthetic_error(), Some(Error::BadHeader)));
+}
+
+#[test]
+fn differing_content_length_with_chunked() {
+    test::set_handler("/differing_content_length_with_chunked", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Length: 4, 6", "Transfer-Encoding: chunked"],
+            "3\r\nhel\r\n0\r\n\r\n".to_string().into_bytes(),
+        )
+    });
+    let resp = get("test://host/differing_content_length_with_chunked").call();
+    assert!(matches!(resp.synthetic_error(), Some(Error::BadHeader)));
+}
+
+#[test]
+fn bare_lf_line_endings_rejected() {
+    test::set_handler("/bare_lf_line_endings_rejected", |_unit| {
+        let stream = Stream::Test(
+            Box::new(std::io::Cursor::new(
+                b"HTTP/1.1 200 OK\nContent-Length: 2\n\nOK".to_vec(),
+            )),
+            vec![],
+        );
+        Ok(stream)
+    });
+    let resp = get("test://host/bare_lf_line_endings_rejected").call();
+    assert!(matches!(resp.synthetic_error(), Some(Error::BadStatus)));
+}
+
+#[test]
+fn bare_lf_line_endings_lenient() {
+    test::set_handler("/bare_lf_line_endings_lenient", |_unit| {
+        let stream = Stream::Test(
+            Box::new(std::io::Cursor::new(
+                b"HTTP/1.1 200 OK\nContent-Length: 2\r\nX-Foo: bar\n\nOK".to_vec(),
+            )),
+            vec![],
+        );
+        Ok(stream)
+    });
+    let resp = get("test://host/bare_lf_line_endings_lenient")
+        .lenient_line_endings()
+        .call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.header("X-Foo"), Some("bar"));
+    assert_eq!(resp.into_string().unwrap(), "OK");
+}
+
+#[test]
+fn content_length_premature_eof() {
+    test::set_handler("/content_length_premature_eof", |_unit| {
+        test::make_response(200, "OK", vec!["Content-Length: 100"], vec![b'a'; 50])
+    });
+    let resp = get("test://host/content_length_premature_eof").call();
+    let mut reader = resp.into_reader();
+    let mut body = vec![];
+    let err = reader.read_to_end(&mut body).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert_eq!(body.len(), 50);
+}
+
+#[test]
+fn content_length_connection_closed() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n")
+            .unwrap();
+        stream.write_all(&[b'a'; 50]).unwrap();
+        // dropping the stream closes the connection.
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port)).call();
+    let err = resp.into_string().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn content_length_premature_eof_connection_close() {
+    test::set_handler("/content_length_premature_eof_connection_close", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Connection: close", "Content-Length: 100"],
+            vec![b'a'; 50],
+        )
+    });
+    let resp = get("test://host/content_length_premature_eof_connection_close").call();
+    let err = resp.into_string().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn content_length_limits_http10_body() {
+    let s = "HTTP/1.0 200 OK\r\n\
+             Content-Length: 5\r\n\
+             \r\n\
+             Hello, and more";
+    let resp = s.parse::<Response>().unwrap();
+    assert_eq!(resp.into_string().unwrap(), "Hello");
+}
+
+#[test]
+fn into_lines() {
+    test::set_handler("/into_lines", |_unit| {
+        test::make_response(200, "OK", vec![], b"first\nsecond\r\n\nlast".to_vec())
+    });
+    let resp = get("test://host/into_lines").call();
+    let lines: Vec<String> = resp.into_lines().map(|l| l.unwrap()).collect();
+    assert_eq!(lines, vec!["first", "second", "", "last"]);
+}
+
+#[test]
+#[cfg(feature = "charset")]
+fn into_lines_charset() {
+    test::set_handler("/into_lines_charset", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Type: text/plain; charset=iso-8859-1"],
+            b"g\xe5\nh\xe4r\n".to_vec(),
+        )
+    });
+    let resp = get("test://host/into_lines_charset").call();
+    let lines: Vec<String> = resp.into_lines().map(|l| l.unwrap()).collect();
+    assert_eq!(lines, vec!["gå", "här"]);
+}
+
+#[test]
+fn header_line_too_long() {
+    test::set_handler("/header_line_too_long", |_unit| {
+        let long = format!("X-Long: {}", "a".repeat(100_000));
+        test::make_response(200, "OK", vec![&long], vec![])
+    });
+    let resp = get("test://host/header_line_too_long").call();
+    assert!(matches!(resp.synthetic_error(), Some(Error::BadHeader)));
+}
+
+#[test]
+fn max_header_line() {
+    test::set_handler("/max_header_line", |_unit| {
+        let long = format!("X-Long: {}", "a".repeat(100_000));
+        test::make_response(200, "OK", vec![&long], vec![])
+    });
+    let resp = get("test://host/max_header_line")
+        .max_header_line(200_000)
+        .call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.header("X-Long").unwrap().len(), 100_000);
+}
+
+#[test]
+fn max_header_line_boundary() {
+    // "X-Long: " and the value make up a line of exactly 100 bytes.
+    test::set_handler("/max_header_line_boundary_max", |_unit| {
+        let long = format!("X-Long: {}", "a".repeat(92));
+        test::make_response(200, "OK", vec![&long], vec![])
+    });
+    let resp = get("test://host/max_header_line_boundary_max")
+        .max_header_line(100)
+        .call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.header("X-Long").unwrap().len(), 92);
+
+    test::set_handler("/max_header_line_boundary_over", |_unit| {
+        let long = format!("X-Long: {}", "a".repeat(93));
+        test::make_response(200, "OK", vec![&long], vec![])
+    });
+    let resp = get("test://host/max_header_line_boundary_over")
+        .max_header_line(100)
+        .call();
+    assert!(matches!(resp.synthetic_error(), Some(Error::BadHeader)));
+
+    // without a '\r' to hold back, the line ending is the only place to check.
+    test::set_handler("/max_header_line_boundary_lf", |_unit| {
+        let response = format!("HTTP/1.1 200 OK\nX-Long: {}\n\n", "a".repeat(93));
+        let stream = Stream::Test(
+            Box::new(std::io::Cursor::new(response.into_bytes())),
+            vec![],
+        );
+        Ok(stream)
+    });
+    let resp = get("test://host/max_header_line_boundary_lf")
+        .lenient_line_endings()
+        .max_header_line(100)
+        .call();
+    assert!(matches!(resp.synthetic_error(), Some(Error::BadHeader)));
+}
+
+#[test]
+fn body_in_same_packet_as_headers() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        let mut response = b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n".to_vec();
+        response.extend_from_slice(&[b'a'; 100]);
+        stream.write_all(&response).unwrap();
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port)).call();
+    let mut body = vec![];
+    resp.into_reader().read_to_end(&mut body).unwrap();
+    assert_eq!(body, vec![b'a'; 100]);
+}
+
+#[test]
+fn into_reader_with_progress() {
+    test::set_handler("/into_reader_with_progress", |_unit| {
+        test::make_response(200, "OK", vec!["Content-Length: 20000"], vec![b'a'; 20000])
+    });
+    let resp = get("test://host/into_reader_with_progress").call();
+    let mut calls = vec![];
+    {
+        let mut reader = resp.into_reader_with_progress(|read, total| calls.push((read, total)));
+        let mut buf = [0_u8; 4096];
+        while reader.read(&mut buf).unwrap() > 0 {}
+    }
+    assert!(calls.len() >= 5);
+    assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(calls.iter().all(|c| c.1 == Some(20000)));
+    assert_eq!(calls.last().unwrap().0, 20000);
+}
+
+#[test]
+fn early_hints_skipped() {
+    test::set_handler("/early_hints_skipped", |_unit| {
+        let stream = Stream::Test(
+            Box::new(std::io::Cursor::new(
+                b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\n\
+                  HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nOK"
+                    .to_vec(),
+            )),
+            vec![],
+        );
+        Ok(stream)
+    });
+    let resp = get("test://host/early_hints_skipped").call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.header("Link"), None);
+    assert_eq!(resp.into_string().unwrap(), "OK");
+}
+
+#[test]
+fn into_events() {
+    test::set_handler("/into_events", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Type: text/event-stream"],
+            b": keep-alive comment\n\
+              data: first\n\
+              \n\
+              id: 1\n\
+              event: update\n\
+              data: line one\r\n\
+              data:line two\n\
+              \n\
+              retry: 1000\n\
+              \n\
+              data\n\
+              \n\
+              data: unfinished"
+                .to_vec(),
+        )
+    });
+    let resp = get("test://host/into_events").call();
+    let events: Vec<Event> = resp.into_events().map(|e| e.unwrap()).collect();
+    assert_eq!(
+        events,
+        vec![
+            Event {
+                id: None,
+                event: "message".to_string(),
+                data: "first".to_string(),
+            },
+            Event {
+                id: Some("1".to_string()),
+                event: "update".to_string(),
+                data: "line one\nline two".to_string(),
+            },
+            Event {
+                id: Some("1".to_string()),
+                event: "message".to_string(),
+                data: "".to_string(),
+            },
+        ]
+    );
+}
+
+fn unframed_response(version: &str) -> Result<Stream, Error> {
+    let response = format!("{} 200 OK\r\n\r\nuntil the end", version);
+    Ok(Stream::Test(
+        Box::new(std::io::Cursor::new(response.into_bytes())),
+        vec![],
+    ))
+}
+
+#[test]
+fn unframed_body_read_to_end() {
+    test::set_handler("/unframed_body_read_to_end", |_unit| {
+        unframed_response("HTTP/1.1")
+    });
+    let resp = get("test://host/unframed_body_read_to_end").call();
+    assert_eq!(resp.into_string().unwrap(), "until the end");
+}
+
+#[test]
+fn unframed_body_strict() {
+    test::set_handler("/unframed_body_strict", |_unit| {
+        unframed_response("HTTP/1.1")
+    });
+    let resp = get("test://host/unframed_body_strict")
+        .strict_framing()
+        .call();
+    assert!(matches!(
+        resp.synthetic_error(),
+        Some(Error::AmbiguousBody(_))
+    ));
+
+    test::set_handler("/unframed_body_strict", |_unit| {
+        unframed_response("HTTP/1.0")
+    });
+    let resp = get("test://host/unframed_body_strict")
+        .strict_framing()
+        .call();
+    assert_eq!(resp.into_string().unwrap(), "until the end");
+
+    test::set_handler("/unframed_body_strict", |_unit| {
+        test::make_response(204, "No Content", vec![], vec![])
+    });
+    let resp = get("test://host/unframed_body_strict")
+        .strict_framing()
+        .call();
+    assert_eq!(resp.status(), 204);
+}
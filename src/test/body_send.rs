@@ -1,4 +1,5 @@
 use crate::test;
+use std::io::{Read, Write};
 
 use super::super::*;
 
@@ -57,6 +58,55 @@ fn content_length_and_chunked() {
     assert!(!s.contains("\r\nContent-Length:\r\n"));
 }
 
+#[test]
+fn content_length_and_transfer_encoding_rejected() {
+    test::set_handler("/content_length_and_transfer_encoding_rejected", |_unit| {
+        panic!("the request must not be sent");
+    });
+    let resp = post("test://host/content_length_and_transfer_encoding_rejected")
+        .set("Transfer-Encoding", "chunked")
+        .set("Content-Length", "14")
+        .send_string("Hello World!!!");
+    assert!(matches!(
+        resp.synthetic_error(),
+        Some(Error::AmbiguousBody(_))
+    ));
+    assert_eq!(resp.status(), 400);
+
+    let resp = post("test://host/content_length_and_transfer_encoding_rejected")
+        .set("Transfer-Encoding", "identity")
+        .set("Content-Length", "14")
+        .send_string("Hello World!!!");
+    assert!(matches!(
+        resp.synthetic_error(),
+        Some(Error::AmbiguousBody(_))
+    ));
+    assert_eq!(resp.status(), 400);
+}
+
+#[test]
+fn conflicting_content_lengths_rejected() {
+    test::set_handler("/conflicting_content_lengths_rejected", |_unit| {
+        panic!("the request must not be sent");
+    });
+    let resp = post("test://host/conflicting_content_lengths_rejected")
+        .set("Content-Length", "14")
+        .set("content-length", "15")
+        .send_string("Hello World!!!");
+    assert!(matches!(
+        resp.synthetic_error(),
+        Some(Error::AmbiguousBody(_))
+    ));
+
+    let resp = post("test://host/conflicting_content_lengths_rejected")
+        .set("Content-Length", "14, 15")
+        .send_string("Hello World!!!");
+    assert!(matches!(
+        resp.synthetic_error(),
+        Some(Error::AmbiguousBody(_))
+    ));
+}
+
 #[test]
 #[cfg(feature = "charset")]
 fn str_with_encoding() {
@@ -109,3 +159,430 @@ fn content_type_not_overriden_on_json() {
     let s = String::from_utf8_lossy(&vec);
     assert!(s.contains("\r\ncontent-type: text/plain\r\n"));
 }
+
+#[test]
+fn content_type_inferred_on_str() {
+    test::set_handler("/content_type_inferred_on_str", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = post("test://host/content_type_inferred_on_str")
+        .infer_content_type()
+        .send_string("Hello World!!!");
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.contains("\r\nContent-Type: text/plain; charset=utf-8\r\n"));
+}
+
+#[test]
+fn content_type_not_inferred_on_str() {
+    test::set_handler("/content_type_not_inferred_on_str", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = post("test://host/content_type_not_inferred_on_str").send_string("Hello World!!!");
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(!s.to_ascii_lowercase().contains("content-type"));
+
+    test::set_handler("/content_type_not_inferred_on_str", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = post("test://host/content_type_not_inferred_on_str")
+        .set("content-type", "text/csv")
+        .infer_content_type()
+        .send_string("a,b");
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.contains("\r\ncontent-type: text/csv\r\n"));
+    assert!(!s.contains("text/plain"));
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip_body() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    test::set_handler("/gzip_body", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let text = "Hello World!!! ".repeat(100);
+    let resp = post("test://host/gzip_body").gzip_body().send_string(&text);
+    let vec = resp.to_write_vec();
+    let split = vec.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let s = String::from_utf8_lossy(&vec[..split]);
+    assert!(s.contains("\r\nContent-Encoding: gzip\r\n"));
+    assert!(s.contains("\r\nTransfer-Encoding: chunked\r\n"));
+    assert!(!s.contains("Content-Length"));
+    let dechunked = chunked_transfer::Decoder::new(&vec[split..]);
+    let mut body = String::new();
+    GzDecoder::new(dechunked).read_to_string(&mut body).unwrap();
+    assert_eq!(body, text);
+    // it's compressible, so fewer bytes on the wire than the text.
+    assert!(vec.len() - split < text.len());
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip_body_not_used_without_body() {
+    test::set_handler("/gzip_body_redirect1", |_unit| {
+        test::make_response(302, "Found", vec!["Location: /gzip_body_redirect2"], vec![])
+    });
+    test::set_handler("/gzip_body_redirect2", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = post("test://host/gzip_body_redirect1")
+        .gzip_body()
+        .send_string("Hello World!!!");
+    assert_eq!(resp.status(), 200);
+    let s = String::from_utf8_lossy(&resp.to_write_vec()).to_string();
+    assert!(s.starts_with("GET /gzip_body_redirect2 HTTP/1.1\r\n"));
+    assert!(!s.contains("Transfer-Encoding"));
+    assert!(!s.contains("Content-Encoding"));
+
+    test::set_handler("/gzip_body_call", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/gzip_body_call").gzip_body().call();
+    let s = String::from_utf8_lossy(&resp.to_write_vec()).to_string();
+    assert!(s.ends_with("\r\n\r\n"));
+    assert!(!s.contains("Transfer-Encoding"));
+    assert!(!s.contains("Content-Encoding"));
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip_body_without_chunking_rejected() {
+    let resp = post("test://host/gzip_body_identity")
+        .set("Transfer-Encoding", "identity")
+        .gzip_body()
+        .send_string("Hello World!!!");
+    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status_text(), "Ambiguous Body");
+
+    let resp = post("test://host/gzip_body_length")
+        .set("Content-Length", "14")
+        .gzip_body()
+        .send_string("Hello World!!!");
+    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status_text(), "Ambiguous Body");
+}
+
+#[test]
+fn content_length_for_empty_body() {
+    for method in &["POST", "PUT", "PATCH"] {
+        let path = format!("/content_length_for_empty_body_{}", method);
+        test::set_handler(&path, |unit| {
+            assert_eq!(unit.header("Content-Length").unwrap(), "0");
+            assert!(!unit.has("Transfer-Encoding"));
+            test::make_response(200, "OK", vec![], vec![])
+        });
+        let resp = request(method, &format!("test://host{}", path)).call();
+        assert_eq!(resp.status(), 200);
+    }
+}
+
+#[test]
+fn no_content_length_for_empty_body() {
+    for method in &["GET", "HEAD", "DELETE", "OPTIONS"] {
+        let path = format!("/no_content_length_for_empty_body_{}", method);
+        test::set_handler(&path, |unit| {
+            assert!(!unit.has("Content-Length"));
+            assert!(!unit.has("Transfer-Encoding"));
+            test::make_response(200, "OK", vec![], vec![])
+        });
+        let resp = request(method, &format!("test://host{}", path)).send_bytes(&[]);
+        assert_eq!(resp.status(), 200);
+    }
+}
+
+#[test]
+fn forced_content_length_for_empty_body() {
+    test::set_handler("/forced_content_length_for_empty_body", |unit| {
+        assert_eq!(unit.all("Content-Length"), vec!["0"]);
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/forced_content_length_for_empty_body")
+        .set("Content-Length", "0")
+        .call();
+    assert_eq!(resp.status(), 200);
+}
+
+#[test]
+fn no_content_length_for_empty_chunked_body() {
+    test::set_handler("/no_content_length_for_empty_chunked_body", |unit| {
+        assert!(!unit.has("Content-Length"));
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = post("test://host/no_content_length_for_empty_chunked_body")
+        .set("Transfer-Encoding", "chunked")
+        .call();
+    assert_eq!(resp.status(), 200);
+}
+
+#[test]
+fn expect_continue_rejected() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let port = test::run_server(move |mut stream| {
+        let head = test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        // anything arriving after the rejection would be the body.
+        stream
+            .set_read_timeout(Some(std::time::Duration::from_millis(200)))
+            .unwrap();
+        let mut rest = vec![];
+        stream.read_to_end(&mut rest).ok();
+        tx.send((head, rest)).unwrap();
+    });
+    let resp = post(&format!("http://127.0.0.1:{}/", port))
+        .set("Expect", "100-continue")
+        .send_string("Hello World!!!");
+    assert_eq!(resp.status(), 417);
+    assert!(!resp.synthetic());
+    drop(resp);
+    let (head, rest) = rx.recv().unwrap();
+    assert!(head.contains("\r\nExpect: 100-continue\r\n"));
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn expect_continue_accepted() {
+    let port = test::run_server(|mut stream| {
+        test::read_request_head(&mut stream);
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+        let mut body = [0_u8; 14];
+        stream.read_exact(&mut body).unwrap();
+        assert_eq!(&body, b"Hello World!!!");
+        stream
+            .write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    let resp = post(&format!("http://127.0.0.1:{}/", port))
+        .set("Expect", "100-continue")
+        .send_string("Hello World!!!");
+    assert_eq!(resp.status(), 201);
+}
+
+#[test]
+fn chunk_size() {
+    test::set_handler("/chunk_size", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = post("test://host/chunk_size")
+        .set("Transfer-Encoding", "chunked")
+        .chunk_size(5)
+        .send_string("Hello World!");
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.ends_with("\r\n\r\n5\r\nHello\r\n5\r\n Worl\r\n2\r\nd!\r\n0\r\n\r\n"));
+}
+
+#[test]
+fn no_chunking_for_identity_transfer_encoding() {
+    test::set_handler("/no_chunking_for_identity_transfer_encoding", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = post("test://host/no_chunking_for_identity_transfer_encoding")
+        .set("Transfer-Encoding", "identity")
+        .send_string("Hello World!!!");
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.ends_with("\r\n\r\nHello World!!!"));
+    // the transfer coding overrides any length, so none is sent.
+    assert!(!s.contains("Content-Length"));
+}
+
+#[test]
+fn content_length_for_buffered_reader() {
+    test::set_handler("/content_length_for_buffered_reader", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let reader = std::io::Cursor::new(b"Hello World!!!".to_vec());
+    let resp = post("test://host/content_length_for_buffered_reader")
+        .buffer_body()
+        .send(reader);
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.contains("\r\nContent-Length: 14\r\n"));
+    assert!(!s.contains("Transfer-Encoding"));
+    assert!(s.ends_with("\r\n\r\nHello World!!!"));
+}
+
+#[test]
+fn content_length_for_buffered_reader_in_file() {
+    test::set_handler("/content_length_for_buffered_reader_in_file", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let body: Vec<u8> = (0..100_000_u32).map(|i| (i % 251) as u8).collect();
+    let reader = std::io::Cursor::new(body.clone());
+    let resp = post("test://host/content_length_for_buffered_reader_in_file")
+        .buffer_body_limit(1000)
+        .send(reader);
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.contains("\r\nContent-Length: 100000\r\n"));
+    assert!(!s.contains("Transfer-Encoding"));
+    assert!(vec.ends_with(&body));
+}
+
+#[test]
+fn retry_with_cloned_request() {
+    let req = post("test://host/retry_with_cloned_request")
+        .set("X-Attempt", "same")
+        .build();
+    let mut sent = vec![];
+    for _ in 0..2 {
+        test::set_handler("/retry_with_cloned_request", |_unit| {
+            test::make_response(200, "OK", vec![], vec![])
+        });
+        let resp = req.build().send_string("Hello World!!!");
+        sent.push(resp.to_write_vec());
+    }
+    assert_eq!(sent[0], sent[1]);
+    assert!(String::from_utf8_lossy(&sent[0]).ends_with("\r\n\r\nHello World!!!"));
+}
+
+#[test]
+fn send_chunks() {
+    test::set_handler("/send_chunks", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let chunks = vec![
+        b"Hello".to_vec(),
+        vec![],
+        b" World".to_vec(),
+        b"!!!".to_vec(),
+    ];
+    let resp = post("test://host/send_chunks")
+        .chunk_size(5)
+        .send_chunks(chunks);
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.contains("\r\nTransfer-Encoding: chunked\r\n"));
+    assert!(!s.contains("Content-Length"));
+    let body = &s[s.find("\r\n\r\n").unwrap() + 4..];
+    assert_eq!(body, "5\r\nHello\r\n5\r\n Worl\r\n4\r\nd!!!\r\n0\r\n\r\n");
+}
+
+#[test]
+fn content_length_for_delete_with_body() {
+    test::set_handler("/content_length_for_delete_with_body", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp =
+        delete("test://host/content_length_for_delete_with_body").send_string("Hello World!!!");
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.starts_with("DELETE "));
+    assert!(s.contains("\r\nContent-Length: 14\r\n"));
+    assert!(s.ends_with("\r\n\r\nHello World!!!"));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn content_length_for_delete_with_json() {
+    test::set_handler("/content_length_for_delete_with_json", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp =
+        delete("test://host/content_length_for_delete_with_json").send_json(json!({ "id": 1 }));
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.contains("\r\nContent-Length: 8\r\n"));
+    assert!(s.ends_with("{\"id\":1}"));
+}
+
+#[test]
+fn no_content_length_for_delete_without_body() {
+    test::set_handler("/no_content_length_for_delete_without_body", |unit| {
+        assert!(!unit.has("Content-Length"));
+        assert!(!unit.has("Transfer-Encoding"));
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = delete("test://host/no_content_length_for_delete_without_body").call();
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.starts_with("DELETE "));
+    assert!(s.ends_with("\r\n\r\n"));
+}
+
+/// Server that hands the client the body chunk by chunk, and only hands out the next
+/// one once the previous has arrived, decoded by `decode`. Responds with the body.
+fn flushing_server<D>(mut decode: D) -> (u16, std::sync::mpsc::Receiver<Vec<u8>>)
+where
+    D: FnMut(&[u8], &mut Vec<u8>) + Send + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::channel();
+    let port = test::run_server(move |mut stream| {
+        test::read_request_head(&mut stream);
+        // a chunk held back by the client makes the read below fail.
+        stream
+            .set_read_timeout(Some(std::time::Duration::from_secs(2)))
+            .unwrap();
+        let mut decoder = chunked_transfer::Decoder::new(stream.try_clone().unwrap());
+        let mut body = vec![];
+        let mut buf = [0_u8; 1024];
+        for (chunk, expected) in &[("hello", "hello"), (" world", "hello world")] {
+            tx.send(chunk.as_bytes().to_vec()).unwrap();
+            while body.len() < expected.len() {
+                let amount = decoder.read(&mut buf).unwrap();
+                decode(&buf[..amount], &mut body);
+            }
+        }
+        drop(tx);
+        let mut rest = vec![];
+        decoder.read_to_end(&mut rest).unwrap();
+        decode(&rest, &mut body);
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len());
+        stream.write_all(head.as_bytes()).unwrap();
+        stream.write_all(&body).unwrap();
+    });
+    (port, rx)
+}
+
+#[test]
+fn flush_body_chunked() {
+    let (port, rx) = flushing_server(|data, body| body.extend_from_slice(data));
+    let resp = post(&format!("http://127.0.0.1:{}/", port))
+        .set("Transfer-Encoding", "chunked")
+        .flush_body()
+        .send_chunks(rx);
+    assert_eq!(resp.into_string().unwrap(), "hello world");
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn flush_body_gzip() {
+    let mut gz = flate2::write::GzDecoder::new(vec![]);
+    let (port, rx) = flushing_server(move |data, body| {
+        gz.write_all(data).unwrap();
+        gz.flush().unwrap();
+        body.extend_from_slice(gz.get_ref());
+        gz.get_mut().clear();
+    });
+    let resp = post(&format!("http://127.0.0.1:{}/", port))
+        .gzip_body()
+        .flush_body()
+        .send_chunks(rx);
+    assert_eq!(resp.into_string().unwrap(), "hello world");
+}
+
+#[test]
+fn response_before_body_is_read() {
+    let port = test::run_server(|mut stream| {
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 413 Payload Too Large\r\nConnection: close\r\nContent-Length: 8\r\n\r\nToo big!")
+            .unwrap();
+        // closing with the body unread resets the connection.
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    });
+    let len = 100 * 1024 * 1024;
+    let resp = post(&format!("http://127.0.0.1:{}/", port))
+        .set("Content-Length", &len.to_string())
+        .send(std::io::repeat(b'x').take(len));
+    assert_eq!(resp.status(), 413);
+    assert!(!resp.synthetic());
+    assert_eq!(resp.into_string().unwrap(), "Too big!");
+}
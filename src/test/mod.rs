@@ -3,8 +3,10 @@ use crate::stream::Stream;
 use crate::unit::Unit;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
-use std::io::{Cursor, Write};
+use std::io::{Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
+use std::thread;
 
 mod agent_test;
 mod auth;
@@ -14,12 +16,13 @@ mod query_string;
 mod range;
 mod redirect;
 mod simple;
+mod timeout;
 
 type RequestHandler = dyn Fn(&Unit) -> Result<Stream, Error> + Send + 'static;
 
 lazy_static! {
     pub(crate) static ref TEST_HANDLERS: Arc<Mutex<HashMap<String, Box<RequestHandler>>>> =
-        { Arc::new(Mutex::new(HashMap::new())) };
+        Arc::new(Mutex::new(HashMap::new()));
 }
 
 pub(crate) fn set_handler<H>(path: &str, handler: H)
@@ -55,3 +58,33 @@ pub(crate) fn resolve_handler(unit: &Unit) -> Result<Stream, Error> {
     let handler = handlers.remove(path).unwrap();
     handler(unit)
 }
+
+/// Start a server on a random loopback port that hands the first accepted
+/// connection to the handler. Returns the port.
+pub(crate) fn run_server<F>(handler: F) -> u16
+where
+    F: FnOnce(TcpStream) + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        if let Ok((stream, _)) = listener.accept() {
+            handler(stream);
+        }
+    });
+    port
+}
+
+/// Read the request prelude (up until and including the empty line) from a
+/// server side connection.
+pub(crate) fn read_request_head(stream: &mut TcpStream) -> String {
+    let mut head = vec![];
+    let mut byte = [0_u8];
+    while !head.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut byte) {
+            Ok(1) => head.push(byte[0]),
+            _ => break,
+        }
+    }
+    String::from_utf8_lossy(&head).to_string()
+}
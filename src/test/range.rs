@@ -1,7 +1,55 @@
+use crate::test;
 use std::io::Read;
 
 use super::super::*;
 
+#[test]
+fn partial_content() {
+    test::set_handler("/partial_content", |unit| {
+        assert_eq!(unit.header("Range").unwrap(), "bytes=10-19");
+        test::make_response(
+            206,
+            "Partial Content",
+            vec!["Content-Range: bytes 10-19/100", "Content-Length: 10"],
+            "0123456789abcdef".to_string().into_bytes(),
+        )
+    });
+    let resp = get("test://host/partial_content").range(10, 19).call();
+    assert_eq!(resp.status(), 206);
+    let range = resp.content_range().unwrap();
+    assert_eq!(
+        range,
+        ContentRange {
+            start: 10,
+            end: 19,
+            total: Some(100),
+        }
+    );
+    assert_eq!(resp.into_string().unwrap(), "0123456789");
+}
+
+#[test]
+fn content_range_unknown_total() {
+    let s = "HTTP/1.1 206 Partial Content\r\n\
+             Content-Range: bytes 0-99/*\r\n\
+             \r\n";
+    let resp = s.parse::<Response>().unwrap();
+    let range = resp.content_range().unwrap();
+    assert_eq!((range.start, range.end, range.total), (0, 99, None));
+}
+
+#[test]
+fn content_range_unparseable() {
+    let s = "HTTP/1.1 416 Range Not Satisfiable\r\n\
+             Content-Range: bytes */100\r\n\
+             \r\n";
+    let resp = s.parse::<Response>().unwrap();
+    assert_eq!(resp.content_range(), None);
+    let s = "HTTP/1.1 200 OK\r\n\r\n";
+    let resp = s.parse::<Response>().unwrap();
+    assert_eq!(resp.content_range(), None);
+}
+
 #[test]
 #[cfg(feature = "tls")]
 fn read_range() {
@@ -41,7 +89,10 @@ fn agent_pool() {
         let state = lock.as_mut().unwrap();
         let pool = state.pool();
         assert_eq!(pool.len(), 1);
-        let f = format!("{:?}", pool.get("ureq.s3.eu-central-1.amazonaws.com", 443));
+        let f = format!(
+            "{:?}",
+            pool.get("https", "ureq.s3.eu-central-1.amazonaws.com", 443)
+        );
         assert_eq!(f, "Some(Stream[https])"); // not a great way of testing.
     }
 
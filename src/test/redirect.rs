@@ -16,6 +16,27 @@ fn redirect_on() {
     assert_eq!(resp.header("x-foo").unwrap(), "bar");
 }
 
+#[test]
+fn redirect_get_url() {
+    test::set_handler("/redirect_get_url1", |_| {
+        test::make_response(
+            302,
+            "Go here",
+            vec!["Location: /redirect_get_url2?page=2"],
+            vec![],
+        )
+    });
+    test::set_handler("/redirect_get_url2", |_| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/redirect_get_url1?page=1").call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.get_url(), "test://host/redirect_get_url2?page=2");
+    let vec = resp.to_write_vec();
+    let s = String::from_utf8_lossy(&vec);
+    assert!(s.starts_with("GET /redirect_get_url2?page=2 HTTP/1.1\r\n"));
+}
+
 #[test]
 fn redirect_many() {
     test::set_handler("/redirect_many1", |_| {
@@ -91,3 +112,124 @@ fn redirect_post() {
     assert!(resp.has("x-foo"));
     assert_eq!(resp.header("x-foo").unwrap(), "bar");
 }
+
+#[test]
+fn redirect_loop() {
+    test::set_handler("/redirect_loop1", |_| {
+        test::make_response(302, "Go here", vec!["Location: /redirect_loop2"], vec![])
+    });
+    test::set_handler("/redirect_loop2", |_| {
+        test::make_response(302, "Go here", vec!["Location: /redirect_loop1"], vec![])
+    });
+    let resp = get("test://host/redirect_loop1").redirects(10).call();
+    assert_eq!(resp.status(), 500);
+    match resp.synthetic_error() {
+        Some(Error::RedirectLoop(url)) => assert_eq!(url, "test://host/redirect_loop1"),
+        e => panic!("Expected redirect loop: {:?}", e),
+    }
+}
+
+#[test]
+fn redirect_steps() {
+    test::set_handler("/redirect_steps1", |_| {
+        test::make_response(301, "Moved", vec!["Location: /redirect_steps2"], vec![])
+    });
+    test::set_handler("/redirect_steps2", |_| {
+        test::make_response(
+            302,
+            "Go here",
+            vec!["Location: test://other/redirect_steps3"],
+            vec![],
+        )
+    });
+    test::set_handler("/redirect_steps3", |_| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/redirect_steps1").call();
+    assert_eq!(resp.status(), 200);
+    let steps = resp.redirects();
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0].status, 301);
+    assert_eq!(steps[0].from, "test://host/redirect_steps1");
+    assert_eq!(steps[0].to, "test://host/redirect_steps2");
+    assert_eq!(steps[1].status, 302);
+    assert_eq!(steps[1].from, "test://host/redirect_steps2");
+    assert_eq!(steps[1].to, "test://other/redirect_steps3");
+}
+
+#[test]
+fn no_redirect_steps() {
+    test::set_handler("/no_redirect_steps", |_| {
+        test::make_response(302, "Go here", vec!["Location: /elsewhere"], vec![])
+    });
+    let resp = get("test://host/no_redirect_steps").redirects(0).call();
+    assert_eq!(resp.status(), 302);
+    assert!(resp.redirects().is_empty());
+}
+
+fn redirect_chain(name: &'static str, hops: usize) {
+    for i in 0..hops {
+        test::set_handler(&format!("/{}{}", name, i), move |_| {
+            let location = format!("Location: /{}{}", name, i + 1);
+            test::make_response(302, "Go here", vec![&location], vec![])
+        });
+    }
+    test::set_handler(&format!("/{}{}", name, hops), |_| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+}
+
+#[test]
+fn redirect_chain_at_limit() {
+    redirect_chain("redirect_chain_at_limit", 50);
+    let resp = get("test://host/redirect_chain_at_limit0")
+        .redirects(50)
+        .call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.redirects().len(), 50);
+    assert_eq!(resp.get_url(), "test://host/redirect_chain_at_limit50");
+}
+
+#[test]
+fn redirect_chain_over_limit() {
+    redirect_chain("redirect_chain_over_limit", 50);
+    let resp = get("test://host/redirect_chain_over_limit0")
+        .redirects(49)
+        .call();
+    assert_eq!(resp.status(), 500);
+    assert_eq!(resp.status_text(), "Too Many Redirects");
+}
+
+#[test]
+#[cfg(feature = "cookie")]
+fn redirect_back_with_cookie() {
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    // every hop of a redirect is a new connection.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().take(3) {
+            let mut stream = stream.unwrap();
+            let head = test::read_request_head(&mut stream);
+            let response = if head.starts_with("GET /login ") {
+                "HTTP/1.1 302 Found\r\nLocation: /a\r\n\
+                 Set-Cookie: session=1; Path=/\r\nContent-Length: 0\r\n\r\n"
+            } else if head.contains("\r\nCookie: session=1\r\n") {
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"
+            } else {
+                "HTTP/1.1 302 Found\r\nLocation: /login\r\nContent-Length: 0\r\n\r\n"
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    let agent = agent();
+    let resp = agent
+        .get(&format!("http://127.0.0.1:{}/a", port))
+        .redirects(5)
+        .call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.redirects().len(), 2);
+    assert_eq!(resp.into_string().unwrap(), "ok");
+}
@@ -2,6 +2,7 @@ use crate::test;
 use std::io::Read;
 
 use super::super::*;
+use crate::stream::Stream;
 
 #[test]
 fn header_passing() {
@@ -48,6 +49,79 @@ fn repeat_x_header() {
     assert_eq!(resp.status(), 200);
 }
 
+#[test]
+fn forwarded_for() {
+    test::set_handler("/forwarded_for", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/forwarded_for")
+        .forwarded_for("203.0.113.7")
+        .forwarded_for("198.51.100.2")
+        .forwarded("for=198.51.100.2;proto=http")
+        .call();
+    let s = String::from_utf8(resp.to_write_vec()).unwrap();
+    assert!(s.contains("\r\nX-Forwarded-For: 203.0.113.7, 198.51.100.2\r\n"));
+    assert_eq!(s.matches("X-Forwarded-For").count(), 1);
+    assert!(s.contains("\r\nForwarded: for=198.51.100.2;proto=http\r\n"));
+}
+
+#[test]
+fn set_header_exact() {
+    test::set_handler("/set_header_exact", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let agent = agent().set("x-amz-date", "20200101T000000Z").build();
+    let resp = agent
+        .get("test://host/set_header_exact")
+        .set_header_exact("X-Amz-Date", "20200102T000000Z")
+        .call();
+    let s = String::from_utf8(resp.to_write_vec()).unwrap();
+    assert!(s.contains("\r\nX-Amz-Date: 20200102T000000Z\r\n"));
+    assert!(!s.contains("x-amz-date"));
+}
+
+#[test]
+fn request_header_inspection() {
+    let mut req = get("test://host/request_header_inspection");
+    req.set("Accept", "x");
+    assert_eq!(req.header("accept"), Some("x"));
+    assert!(req.has("ACCEPT"));
+    assert_eq!(req.all("accept"), vec!["x"]);
+    assert!(!req.has("X-Other"));
+}
+
+#[test]
+fn conditional_request() {
+    test::set_handler("/conditional_request", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec![
+                "ETag: \"abc123\"",
+                "Last-Modified: Wed, 21 Oct 2015 07:28:00 GMT",
+            ],
+            b"Hello".to_vec(),
+        )
+    });
+    let first = get("test://host/conditional_request").call();
+    assert!(!first.not_modified());
+
+    test::set_handler("/conditional_request", |unit| {
+        assert_eq!(unit.header("If-None-Match"), Some("\"abc123\""));
+        assert_eq!(
+            unit.header("If-Modified-Since"),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+        // the length of the body we would have sent.
+        test::make_response(304, "Not Modified", vec!["Content-Length: 5"], vec![])
+    });
+    let second = get("test://host/conditional_request")
+        .conditional_on(&first)
+        .call();
+    assert!(second.not_modified());
+    assert_eq!(second.into_string().unwrap(), "");
+}
+
 #[test]
 fn body_as_text() {
     test::set_handler("/body_as_text", |_unit| {
@@ -86,6 +160,42 @@ fn body_as_reader() {
     assert_eq!(text, "abcdefgh");
 }
 
+#[test]
+fn body_copy_to() {
+    test::set_handler("/body_copy_to", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Length: 8"],
+            "abcdefghijkl".to_string().into_bytes(),
+        )
+    });
+    let resp = get("test://host/body_copy_to").call();
+    let mut out = vec![];
+    let len = resp.copy_to(&mut out).unwrap();
+    assert_eq!(len, 8);
+    assert_eq!(out, b"abcdefgh");
+}
+
+#[test]
+fn body_copy_to_chunked() {
+    test::set_handler("/body_copy_to_chunked", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Transfer-Encoding: chunked"],
+            "3\r\nabc\r\n5\r\ndefgh\r\n0\r\n\r\n"
+                .to_string()
+                .into_bytes(),
+        )
+    });
+    let resp = get("test://host/body_copy_to_chunked").call();
+    let mut out = vec![];
+    let len = resp.copy_to(&mut out).unwrap();
+    assert_eq!(len, 8);
+    assert_eq!(out, b"abcdefgh");
+}
+
 #[test]
 fn escape_path() {
     test::set_handler("/escape_path%20here", |_unit| {
@@ -164,3 +274,313 @@ pub fn header_with_spaces_before_value() {
         .call();
     assert_eq!(resp.status(), 200);
 }
+
+#[test]
+pub fn call_result_transport_error() {
+    let result = get("borkedscheme://host/call_result").call_result();
+    match result {
+        Err(Error::UnknownScheme(scheme)) => assert_eq!(scheme, "borkedscheme"),
+        r => panic!("Expected unknown scheme error: {:?}", r),
+    }
+}
+
+#[test]
+pub fn unknown_scheme_error() {
+    let err = get("ftp://host/unknown_scheme_error")
+        .call_result()
+        .unwrap_err();
+    assert!(err.is_unknown_scheme());
+    assert_eq!(err.unknown_scheme(), Some("ftp"));
+    assert_eq!(err.status(), 400);
+
+    let resp = get("ftp://host/unknown_scheme_error").call();
+    assert!(resp.synthetic_error().as_ref().unwrap().is_unknown_scheme());
+}
+
+#[test]
+pub fn call_result_http_error() {
+    test::set_handler("/call_result_http_error", |_unit| {
+        test::make_response(404, "Not Found", vec![], vec![])
+    });
+    let resp = get("test://host/call_result_http_error")
+        .call_result()
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+    assert!(!resp.synthetic());
+}
+
+#[test]
+pub fn call_synthetic_keeps_url() {
+    test::set_handler("/call_synthetic_keeps_url", |_unit| {
+        let stream = Stream::Test(Box::new(std::io::Cursor::new(b"garbage".to_vec())), vec![]);
+        Ok(stream)
+    });
+    let resp = get("test://host/call_synthetic_keeps_url").call();
+    assert!(resp.synthetic());
+    assert_eq!(resp.get_url(), "test://host/call_synthetic_keeps_url");
+}
+
+#[test]
+fn head_body_is_empty() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        // keep the connection open, reading until close would hang.
+        std::thread::sleep(std::time::Duration::from_secs(2));
+    });
+    let start = std::time::Instant::now();
+    let resp = head(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.header("Content-Length"), Some("100"));
+    assert_eq!(resp.into_string().unwrap(), "");
+    assert!(start.elapsed() < std::time::Duration::from_secs(1));
+}
+
+#[test]
+fn no_tls_info_without_tls() {
+    test::set_handler("/no_tls_info_without_tls", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/no_tls_info_without_tls").call();
+    assert!(resp.tls_info().is_none());
+}
+
+#[test]
+fn remote_addr() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port)).call();
+    let addr = resp.remote_addr().unwrap();
+    assert_eq!(addr, format!("127.0.0.1:{}", port).parse().unwrap());
+}
+
+#[test]
+fn host_header_with_port() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        let head = test::read_request_head(&mut stream);
+        let host = head
+            .lines()
+            .find(|l| l.starts_with("Host: "))
+            .unwrap()
+            .to_string();
+        let resp = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+            host.len(),
+            host
+        );
+        stream.write_all(resp.as_bytes()).unwrap();
+    });
+    let resp = get(&format!("http://localhost:{}/", port)).call();
+    assert_eq!(
+        resp.into_string().unwrap(),
+        format!("Host: localhost:{}", port)
+    );
+}
+
+#[test]
+fn host_header_default_port() {
+    test::set_handler("/host_header_default_port", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/host_header_default_port").call();
+    let s = String::from_utf8_lossy(&resp.to_write_vec()).to_string();
+    assert!(s.contains("\r\nHost: host\r\n"));
+
+    for (url, host) in &[
+        ("https://example.com/", "example.com"),
+        ("http://example.com:80/", "example.com"),
+        ("http://example.com:8080/", "example.com:8080"),
+        ("https://example.com:8443/", "example.com:8443"),
+        ("http://[::1]:8080/", "[::1]:8080"),
+    ] {
+        let url = url::Url::parse(url).unwrap();
+        assert_eq!(&crate::unit::host_header(&url), host);
+    }
+}
+
+#[test]
+fn host_header_set_by_user() {
+    test::set_handler("/host_header_set_by_user", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host:8080/host_header_set_by_user")
+        .set("Host", "example.com")
+        .call();
+    let s = String::from_utf8_lossy(&resp.to_write_vec()).to_string();
+    assert!(s.contains("\r\nHost: example.com\r\n"));
+    assert!(!s.contains("host:8080"));
+}
+
+#[test]
+fn host_header_idn() {
+    // the url crate applies IDNA for http(s), so DNS, SNI and the Host
+    // header all get the punycode form.
+    let url = url::Url::parse("http://bücher.example:8080/").unwrap();
+    assert_eq!(url.host_str(), Some("xn--bcher-kva.example"));
+    assert_eq!(crate::unit::host_header(&url), "xn--bcher-kva.example:8080");
+
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    // a fullwidth "L" is mapped to a plain "l" by IDNA, which gives us a
+    // non-ascii hostname that resolves without a custom resolver.
+    let resp = get(&format!("http://\u{ff2c}ocalhost:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.get_url(), format!("http://localhost:{}/", port));
+}
+
+#[test]
+fn options_asterisk() {
+    test::set_handler("/*", |_unit| {
+        test::make_response(200, "OK", vec!["Allow: GET, OPTIONS"], vec![])
+    });
+    let resp = options("test://host/*").call();
+    assert_eq!(resp.header("Allow"), Some("GET, OPTIONS"));
+    let s = String::from_utf8_lossy(&resp.to_write_vec()).to_string();
+    assert!(s.starts_with("OPTIONS * HTTP/1.1\r\n"));
+    assert!(s.contains("\r\nHost: host\r\n"));
+}
+
+#[test]
+fn path_is_percent_encoded() {
+    test::set_handler("/path%20with%20space/h%C3%A4r", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/path with space/här?q=1").call();
+    let s = String::from_utf8_lossy(&resp.to_write_vec()).to_string();
+    assert!(
+        s.starts_with("GET /path%20with%20space/h%C3%A4r?q=1 HTTP/1.1\r\n"),
+        "{}",
+        s
+    );
+}
+
+#[test]
+fn path_keeps_encoded_reserved_characters() {
+    test::set_handler("/a%2Fb%3Fc", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/a%2Fb%3Fc?d=e").call();
+    let s = String::from_utf8_lossy(&resp.to_write_vec()).to_string();
+    assert!(s.starts_with("GET /a%2Fb%3Fc?d=e HTTP/1.1\r\n"), "{}", s);
+}
+
+#[test]
+fn options_path() {
+    test::set_handler("/options_path", |_unit| {
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = options("test://host/options_path").call();
+    let s = String::from_utf8_lossy(&resp.to_write_vec()).to_string();
+    assert!(s.starts_with("OPTIONS /options_path HTTP/1.1\r\n"));
+}
+
+#[test]
+fn call_head_only() {
+    test::set_handler("/call_head_only", |_unit| {
+        test::make_response(
+            200,
+            "OK",
+            vec!["Content-Length: 5", "X-Foo: bar"],
+            b"hello".to_vec(),
+        )
+    });
+    let resp = get("test://host/call_head_only").call_head_only();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.header("x-foo"), Some("bar"));
+    assert_eq!(resp.header("content-length"), Some("5"));
+    assert_eq!(resp.into_string().unwrap(), "");
+}
+
+#[test]
+fn try_clone_network_body() {
+    test::set_handler("/try_clone_network_body", |_unit| {
+        test::make_response(200, "OK", vec![], b"hello".to_vec())
+    });
+    let resp = get("test://host/try_clone_network_body").call();
+    assert!(resp.try_clone().is_err());
+    assert_eq!(resp.into_string().unwrap(), "hello");
+}
+
+#[test]
+fn into_stream() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(
+                b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: ping\r\n\r\n",
+            )
+            .unwrap();
+        let mut ping = [0; 5];
+        stream.read_exact(&mut ping).unwrap();
+        assert_eq!(&ping, b"ping\n");
+        stream.write_all(b"pong\n").unwrap();
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port))
+        .set("Connection", "Upgrade")
+        .set("Upgrade", "ping")
+        .call();
+    assert_eq!(resp.status(), 101);
+    let mut conn = resp.into_stream().unwrap();
+    std::io::Write::write_all(&mut conn, b"ping\n").unwrap();
+    let mut pong = String::new();
+    conn.read_to_string(&mut pong).unwrap();
+    assert_eq!(pong, "pong\n");
+}
+
+#[test]
+fn into_stream_unframed_body() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream.write_all(b"HTTP/1.1 200 OK\r\n\r\nhello").unwrap();
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.into_stream().is_none());
+}
+
+#[test]
+fn into_stream_unread_body() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello")
+            .unwrap();
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.into_stream().is_none());
+}
+
+#[test]
+fn into_stream_empty_body() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        stream.write_all(b"more").unwrap();
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    let mut conn = resp.into_stream().unwrap();
+    let mut more = String::new();
+    conn.read_to_string(&mut more).unwrap();
+    assert_eq!(more, "more");
+}
@@ -0,0 +1,156 @@
+use crate::test;
+use std::io::Read;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::super::*;
+
+#[test]
+fn timeouts_from_duration() {
+    test::set_handler("/timeouts_from_duration", |unit| {
+        assert_eq!(unit.timeout_connect, 1_500);
+        assert_eq!(unit.timeout_read, 2_000);
+        assert_eq!(unit.timeout_write, 1);
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/timeouts_from_duration")
+        .timeout_connect(Duration::from_millis(1_500))
+        .timeout_read(Duration::from_secs(2))
+        .timeout_write(Duration::from_micros(10))
+        .call();
+    assert_eq!(resp.status(), 200);
+}
+
+#[test]
+fn timeout_sets_all() {
+    test::set_handler("/timeout_sets_all", |unit| {
+        assert_eq!(unit.timeout_connect, 3_000);
+        assert_eq!(unit.timeout_read, 3_000);
+        assert_eq!(unit.timeout_write, 3_000);
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/timeout_sets_all")
+        .timeout(Duration::from_secs(3))
+        .call();
+    assert_eq!(resp.status(), 200);
+}
+
+#[test]
+fn zero_timeout_is_no_timeout() {
+    test::set_handler("/zero_timeout_is_no_timeout", |unit| {
+        assert_eq!(unit.timeout_connect, 0);
+        assert_eq!(unit.timeout_read, 0);
+        assert_eq!(unit.timeout_write, 0);
+        test::make_response(200, "OK", vec![], vec![])
+    });
+    let resp = get("test://host/zero_timeout_is_no_timeout")
+        .timeout(Duration::from_secs(0))
+        .call();
+    assert_eq!(resp.status(), 200);
+}
+
+#[test]
+fn read_timeout_applied_to_stream() {
+    let port = test::run_server(|mut stream| {
+        // read the request, but never respond.
+        test::read_request_head(&mut stream);
+        thread::sleep(Duration::from_secs(2));
+    });
+    let start = Instant::now();
+    let resp = get(&format!("http://127.0.0.1:{}/", port))
+        .timeout_read(Duration::from_millis(100))
+        .call();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(resp.synthetic());
+    assert_eq!(resp.status(), 500);
+}
+
+#[test]
+#[cfg(feature = "tls")]
+fn connect_timeout_covers_tls_handshake() {
+    let port = test::run_server(|_stream| {
+        // accept the TCP connection, but never answer the ClientHello.
+        thread::sleep(Duration::from_secs(2));
+    });
+    let start = Instant::now();
+    let resp = get(&format!("https://localhost:{}/", port))
+        .timeout_connect(Duration::from_millis(200))
+        .call();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    match resp.synthetic_error() {
+        Some(Error::ConnectionFailed(msg)) => assert_eq!(msg, "TLS handshake timed out"),
+        e => panic!("Expected connection failed: {:?}", e),
+    }
+}
+
+#[test]
+fn connect_retries_after_refused() {
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    // find a free port, and leave it closed so the first attempt is refused.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let server = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(150));
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port))
+        .connect_retries(5, Duration::from_millis(50))
+        .call();
+    assert_eq!(resp.status(), 200);
+    server.join().unwrap();
+}
+
+#[test]
+fn connect_retries_bounded_by_timeout() {
+    use std::net::TcpListener;
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let start = Instant::now();
+    let resp = get(&format!("http://127.0.0.1:{}/", port))
+        .timeout_connect(Duration::from_millis(300))
+        .connect_retries(10, Duration::from_millis(100))
+        .call();
+    assert!(start.elapsed() < Duration::from_millis(500));
+    assert!(matches!(
+        resp.synthetic_error(),
+        Some(Error::ConnectionFailed(_))
+    ));
+}
+
+#[test]
+fn body_read_timeout() {
+    let port = test::run_server(|mut stream| {
+        use std::io::Write;
+        test::read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n")
+            .unwrap();
+        // the rest of the body never comes.
+        thread::sleep(Duration::from_secs(2));
+    });
+    let resp = get(&format!("http://127.0.0.1:{}/", port)).call();
+    assert_eq!(resp.status(), 200);
+    let start = Instant::now();
+    let mut reader = resp.into_reader_with_timeout(Duration::from_millis(100));
+    let mut body = vec![];
+    let err = reader.read_to_end(&mut body).unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(
+        err.kind() == std::io::ErrorKind::WouldBlock || err.kind() == std::io::ErrorKind::TimedOut
+    );
+    assert_eq!(body, b"hello");
+}
@@ -1,7 +1,7 @@
-use std::io::{Result as IoResult, Write};
+use std::io::{Read, Result as IoResult, Write};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use base64;
 #[cfg(feature = "cookie")]
 use cookie::{Cookie, CookieJar};
 use qstring::QString;
@@ -10,8 +10,9 @@ use url::Url;
 use crate::agent::AgentState;
 use crate::body::{self, Payload, SizedReader};
 use crate::header;
+use crate::response::{self, ParseOptions};
 use crate::stream::{self, connect_https, connect_test, Stream};
-use crate::{Error, Header, Request, Response};
+use crate::{Error, Header, RedirectStep, Request, Response};
 
 use crate::pool::DEFAULT_HOST;
 
@@ -23,45 +24,87 @@ pub(crate) struct Unit {
     pub agent: Arc<Mutex<Option<AgentState>>>,
     pub url: Url,
     pub is_chunked: bool,
+    pub is_gzip: bool,
+    pub chunk_size: usize,
+    pub flush_body: bool,
     pub query_string: String,
     pub headers: Vec<Header>,
     pub timeout_connect: u64,
     pub timeout_read: u64,
     pub timeout_write: u64,
+    pub connect_retries: u32,
+    pub connect_backoff: u64,
+    pub parse_options: ParseOptions,
+    pub drain_limit: usize,
+    pub no_cookies: bool,
+    pub strict_framing: bool,
+    pub default_content_type: Option<String>,
     pub method: String,
 }
 
 impl Unit {
     //
 
-    pub(crate) fn new(req: &Request, url: &Url, mix_queries: bool, body: &SizedReader) -> Self {
+    pub(crate) fn new(
+        req: &Request,
+        method: &str,
+        url: &Url,
+        mix_queries: bool,
+        body: &SizedReader,
+    ) -> Self {
         //
 
-        let is_chunked = req
-            .header("transfer-encoding")
+        // there is nothing to compress without a body, like for the GET that
+        // follows a redirect.
+        let is_gzip = req.gzip_body && body.size != Some(0);
+
+        let is_chunked = if req.has("transfer-encoding") {
             // if the user has set an encoding header, obey that.
-            .map(|enc| !enc.is_empty())
+            header::is_chunked(&header::transfer_codings(&req.headers))
+        } else {
+            // the compressed size is unknown, so a gzip body must be chunked.
             // otherwise, no chunking.
-            .unwrap_or(false);
+            is_gzip
+        };
 
-        let query_string = combine_query(&url, &req.query, mix_queries);
+        let query_string = combine_query(url, &req.query, mix_queries);
 
-        let cookie_headers: Vec<_> = extract_cookies(&req.agent, &url);
+        let cookie_headers: Vec<_> = if req.no_cookies {
+            vec![]
+        } else {
+            extract_cookies(&req.agent, url, &req.headers)
+        };
 
         let extra_headers = {
             let mut extra = vec![];
 
-            // chunking and Content-Length headers are mutually exclusive
+            if is_gzip {
+                if !req.has("transfer-encoding") {
+                    extra.push(Header::new("Transfer-Encoding", "chunked"));
+                }
+                if !req.has("content-encoding") {
+                    extra.push(Header::new("Content-Encoding", "gzip"));
+                }
+            }
+
+            // Transfer-Encoding and Content-Length headers are mutually exclusive,
+            // any transfer coding overrides the length.
             // also don't write this if the user has set it themselves
-            if !is_chunked && !req.has("content-length") {
-                if let Some(size) = body.size {
-                    extra.push(Header::new("Content-Length", &format!("{}", size)));
+            if !is_chunked && !req.has("transfer-encoding") && !req.has("content-length") {
+                match body.size {
+                    // an empty body is only worth a header for methods that
+                    // are expected to have one.
+                    Some(0) if !method_expects_body(method) => (),
+                    Some(size) => {
+                        extra.push(Header::new("Content-Length", &format!("{}", size)));
+                    }
+                    None => (),
                 }
             }
 
             let username = url.username();
             let password = url.password().unwrap_or("");
-            if (username != "" || password != "") && !req.has("authorization") {
+            if (!username.is_empty() || !password.is_empty()) && !req.has("authorization") {
                 let encoded = base64::encode(&format!("{}:{}", username, password));
                 extra.push(Header::new("Authorization", &format!("Basic {}", encoded)));
             }
@@ -80,12 +123,31 @@ impl Unit {
             agent: Arc::clone(&req.agent),
             url: url.clone(),
             is_chunked,
+            is_gzip,
+            chunk_size: match req.chunk_size {
+                0 => body::DEFAULT_CHUNK_SIZE,
+                size => size,
+            },
+            flush_body: req.flush_body,
             query_string,
             headers,
             timeout_connect: req.timeout_connect,
             timeout_read: req.timeout_read,
             timeout_write: req.timeout_write,
-            method: req.method.clone(),
+            connect_retries: req.connect_retries,
+            connect_backoff: req.connect_backoff,
+            parse_options: ParseOptions {
+                lenient_line_endings: req.lenient_line_endings,
+                max_line_length: match req.max_line_length {
+                    0 => response::DEFAULT_MAX_LINE_LENGTH,
+                    len => len,
+                },
+            },
+            drain_limit: req.drain_limit,
+            no_cookies: req.no_cookies,
+            strict_framing: req.strict_framing,
+            default_content_type: req.default_content_type.clone(),
+            method: method.to_string(),
         }
     }
 
@@ -94,51 +156,161 @@ impl Unit {
     }
 
     #[cfg(test)]
-    pub fn header<'a>(&self, name: &'a str) -> Option<&str> {
+    pub fn header(&self, name: &str) -> Option<&str> {
         header::get_header(&self.headers, name)
     }
     #[cfg(test)]
-    pub fn has<'a>(&self, name: &'a str) -> bool {
+    pub fn has(&self, name: &str) -> bool {
         header::has_header(&self.headers, name)
     }
     #[cfg(test)]
-    pub fn all<'a>(&self, name: &'a str) -> Vec<&str> {
+    pub fn all(&self, name: &str) -> Vec<&str> {
         header::get_all_headers(&self.headers, name)
     }
 }
 
-/// Perform a connection. Used recursively for redirects.
+/// A request in a redirect chain, that was answered with a redirect.
+///
+/// *Internal API*
+#[derive(Debug)]
+pub(crate) struct Visited {
+    method: String,
+    url: Url,
+    status: u16,
+    // the cookies sent, going back to a url with other cookies is no loop.
+    cookies: Vec<String>,
+}
+
+/// What to do after one request of a call.
+enum Next {
+    /// The response of the call.
+    Done(Box<Response>),
+    /// Make another request, for a redirect or to retry on a fresh connection.
+    Again {
+        unit: Box<Unit>,
+        use_pooled: bool,
+        body: SizedReader,
+        redir: bool,
+    },
+}
+
+/// Perform a connection, following redirects.
+///
+/// `visited` holds each request in the redirect chain before this one.
 pub(crate) fn connect(
+    req: &Request,
+    mut unit: Unit,
+    mut use_pooled: bool,
+    mut visited: Vec<Visited>,
+    mut body: SizedReader,
+    mut redir: bool,
+) -> Result<Response, Error> {
+    // one request per turn, so a long redirect chain doesn't grow the stack.
+    loop {
+        match connect_once(req, unit, use_pooled, &mut visited, body, redir)? {
+            Next::Done(resp) => return Ok(*resp),
+            Next::Again {
+                unit: next_unit,
+                use_pooled: next_use_pooled,
+                body: next_body,
+                redir: next_redir,
+            } => {
+                unit = *next_unit;
+                use_pooled = next_use_pooled;
+                body = next_body;
+                redir = next_redir;
+            }
+        }
+    }
+}
+
+/// Make one request and decide whether it answers the call.
+fn connect_once(
     req: &Request,
     unit: Unit,
     use_pooled: bool,
-    redirect_count: u32,
-    body: SizedReader,
+    visited: &mut Vec<Visited>,
+    mut body: SizedReader,
     redir: bool,
-) -> Result<Response, Error> {
+) -> Result<Next, Error> {
     //
 
+    check_body_framing(&unit)?;
+
     // open socket
     let (mut stream, is_recycled) = connect_socket(&unit, use_pooled)?;
 
-    let send_result = send_prelude(&unit, &mut stream, redir);
+    let expect_continue = expects_continue(&unit, &body);
+
+    // a small body goes out in the same write as the prelude.
+    let small_body = if expect_continue {
+        None
+    } else {
+        take_small_body(&unit, &mut body)?
+    };
+
+    let send_result = send_prelude(&unit, &mut stream, redir, small_body.as_deref());
 
     if let Err(err) = send_result {
         if is_recycled {
             // we try open a new connection, this time there will be
             // no connection in the pool. don't use it.
-            return connect(req, unit, false, redirect_count, body, redir);
+            let body = match small_body {
+                Some(bytes) => Payload::Bytes(bytes).into_read(),
+                None => body,
+            };
+            return Ok(Next::Again {
+                unit: Box::new(unit),
+                use_pooled: false,
+                body,
+                redir,
+            });
         } else {
             // not a pooled connection, propagate the error.
             return Err(err.into());
         }
     }
 
+    // with "Expect: 100-continue" the server gets a chance to refuse the body
+    // before we send it.
+    if expect_continue {
+        if let Some(mut resp) = wait_for_continue(&unit, &mut stream)? {
+            if !unit.no_cookies {
+                save_cookies(&unit, &resp);
+            }
+            // the body was never sent, and we don't know whether the server
+            // will wait for it or not. that makes the connection unusable for
+            // any further requests, which we achieve by not giving the response
+            // a unit to return the connection with.
+            crate::response::set_redirects(&mut resp, redirect_steps(visited, &unit.url));
+            crate::response::set_stream(&mut resp, unit.url.to_string(), None, stream);
+            return Ok(Next::Done(Box::new(resp)));
+        }
+    }
+
     // send the body (which can be empty now depending on redirects)
-    let body_bytes_sent = body::send_body(body, unit.is_chunked, &mut stream)?;
+    let small_body_len = small_body.map(|b| b.len() as u64).unwrap_or(0);
+    let body_bytes_sent = match body::send_body(body, &unit, &mut stream) {
+        Ok(n) => small_body_len + n,
+        Err(err) => {
+            // the server might have answered without reading all of the body, and
+            // closed the connection. its answer says more than the write error.
+            let mut resp = match read_early_response(&unit, &mut stream) {
+                Some(resp) => resp,
+                None => return Err(err.into()),
+            };
+            if !unit.no_cookies {
+                save_cookies(&unit, &resp);
+            }
+            // the connection is broken, it must not go back to the pool.
+            crate::response::set_redirects(&mut resp, redirect_steps(visited, &unit.url));
+            crate::response::set_stream(&mut resp, unit.url.to_string(), None, stream);
+            return Ok(Next::Done(Box::new(resp)));
+        }
+    };
 
     // start reading the response to process cookies and redirects.
-    let mut resp = Response::from_read(&mut stream);
+    let mut resp = crate::response::read_final(&mut stream, unit.parse_options);
 
     if let Some(err) = resp.synthetic_error() {
         if err.is_bad_status_read() && body_bytes_sent == 0 && is_recycled {
@@ -148,16 +320,23 @@ pub(crate) fn connect(
             // body bytes. This is because we currently don't want to buffer
             // any body to be able to replay it.
             let empty = Payload::Empty.into_read();
-            return connect(req, unit, false, redirect_count, empty, redir);
+            return Ok(Next::Again {
+                unit: Box::new(unit),
+                use_pooled: false,
+                body: empty,
+                redir,
+            });
         }
     }
 
     // squirrel away cookies
-    save_cookies(&unit, &resp);
+    if !unit.no_cookies {
+        save_cookies(&unit, &resp);
+    }
 
     // handle redirects
     if resp.redirect() && req.redirects > 0 {
-        if redirect_count == req.redirects {
+        if visited.len() as u32 == req.redirects {
             return Err(Error::TooManyRedirects);
         }
 
@@ -171,39 +350,193 @@ pub(crate) fn connect(
                 .map_err(|_| Error::BadUrl(format!("Bad redirection: {}", location)))?;
 
             // perform the redirect differently depending on 3xx code.
+            #[allow(clippy::single_match)]
             match resp.status() {
-                301 | 302 | 303 => {
+                301..=303 => {
                     let empty = Payload::Empty.into_read();
-                    // recreate the unit to get a new hostname and cookies for the new host.
-                    let mut new_unit = Unit::new(req, &new_url, false, &empty);
                     // this is to follow how curl does it. POST, PUT etc change
                     // to GET on a redirect.
-                    new_unit.method = match &unit.method[..] {
-                        "GET" | "HEAD" => unit.method,
-                        _ => "GET".into(),
+                    let method = match &unit.method[..] {
+                        "GET" | "HEAD" => &unit.method,
+                        _ => "GET",
                     };
-                    return connect(req, new_unit, use_pooled, redirect_count + 1, empty, true);
+                    visited.push(Visited {
+                        method: unit.method.clone(),
+                        url: unit.url.clone(),
+                        status: resp.status(),
+                        cookies: sent_cookies(&unit),
+                    });
+                    // recreate the unit to get a new hostname and cookies for the new host.
+                    let new_unit = Unit::new(req, method, &new_url, false, &empty);
+                    // going back to a place we've already been, with the same
+                    // cookies, will just keep going around in circles.
+                    let cookies = sent_cookies(&new_unit);
+                    if visited
+                        .iter()
+                        .any(|v| v.method == method && v.url == new_url && v.cookies == cookies)
+                    {
+                        return Err(Error::RedirectLoop(new_url.to_string()));
+                    }
+                    return Ok(Next::Again {
+                        unit: Box::new(new_unit),
+                        use_pooled,
+                        body: empty,
+                        redir: true,
+                    });
                 }
                 _ => (),
                 // reinstate this with expect-100
-                // 307 | 308 | _ => connect(unit, method, use_pooled, redirects - 1, body),
+                // 307 | 308 | _ => Next::Again with the same method and body,
             };
         }
     }
 
+    if unit.strict_framing && resp.synthetic_error().is_none() && resp.is_unframed(unit.is_head()) {
+        return Err(Error::AmbiguousBody(
+            "response has neither Content-Length nor chunked Transfer-Encoding".to_string(),
+        ));
+    }
+
     // since it is not a redirect, or we're not following redirects,
     // give away the incoming stream to the response object
+    crate::response::set_redirects(&mut resp, redirect_steps(visited, &unit.url));
     crate::response::set_stream(&mut resp, unit.url.to_string(), Some(unit), stream);
 
     // release the response
-    Ok(resp)
+    Ok(Next::Done(Box::new(resp)))
+}
+
+fn sent_cookies(unit: &Unit) -> Vec<String> {
+    header::get_all_headers(&unit.headers, "cookie")
+        .into_iter()
+        .map(|c| c.to_string())
+        .collect()
+}
+
+/// Each visited url redirected to the next one, and the last to the current url.
+fn redirect_steps(visited: &[Visited], current: &Url) -> Vec<RedirectStep> {
+    visited
+        .iter()
+        .enumerate()
+        .map(|(i, v)| RedirectStep {
+            status: v.status,
+            from: v.url.to_string(),
+            to: visited
+                .get(i + 1)
+                .map(|n| &n.url)
+                .unwrap_or(current)
+                .to_string(),
+        })
+        .collect()
+}
+
+/// A body of a known size up to this is sent in the same write as the prelude,
+/// to not have it wait for a packet of its own.
+const SMALL_BODY_SIZE: usize = 4096;
+
+/// Read a body that is small enough to be sent with the prelude. The body must
+/// also go out as is, without chunking or compression.
+fn take_small_body(unit: &Unit, body: &mut SizedReader) -> IoResult<Option<Vec<u8>>> {
+    let size = match body.size {
+        Some(size) if size > 0 && size <= SMALL_BODY_SIZE => size,
+        _ => return Ok(None),
+    };
+    if unit.is_chunked || unit.is_gzip || unit.flush_body {
+        return Ok(None);
+    }
+    let mut bytes = Vec::with_capacity(size);
+    (&mut body.reader)
+        .take(size as u64)
+        .read_to_end(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+/// How long to wait for a `100 Continue` before sending the body anyway.
+/// Servers that don't understand the expectation never answer.
+const CONTINUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+fn expects_continue(unit: &Unit, body: &SizedReader) -> bool {
+    body.size != Some(0)
+        && header::get_header(&unit.headers, "expect")
+            .map(|v| v.eq_ignore_ascii_case("100-continue"))
+            .unwrap_or(false)
+}
+
+/// Wait for the interim response to `Expect: 100-continue`. Returns the
+/// response if the server answered with a final status instead, in which case
+/// the body must not be sent.
+fn wait_for_continue(unit: &Unit, stream: &mut Stream) -> Result<Option<Response>, Error> {
+    let timeout = match unit.timeout_read {
+        0 => CONTINUE_TIMEOUT,
+        millis => Duration::from_millis(millis).min(CONTINUE_TIMEOUT),
+    };
+    stream.set_read_timeout(Some(timeout));
+    let interim = crate::response::read_interim(&mut *stream, unit.parse_options);
+    stream.set_unit_timeouts(unit);
+    interim
+}
+
+/// How long to wait for a response after failing to send the body.
+const EARLY_RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Read a response the server sent before the body was written, if there is one.
+fn read_early_response(unit: &Unit, stream: &mut Stream) -> Option<Response> {
+    let timeout = match unit.timeout_read {
+        0 => EARLY_RESPONSE_TIMEOUT,
+        millis => Duration::from_millis(millis).min(EARLY_RESPONSE_TIMEOUT),
+    };
+    stream.set_read_timeout(Some(timeout));
+    let resp = crate::response::read_final(&mut *stream, unit.parse_options);
+    stream.set_unit_timeouts(unit);
+    if resp.synthetic() {
+        None
+    } else {
+        Some(resp)
+    }
+}
+
+/// Refuse to send requests where a server could disagree with us on where the body
+/// ends, since that is how requests get smuggled past proxies.
+fn check_body_framing(unit: &Unit) -> Result<(), Error> {
+    let has_length = header::has_header(&unit.headers, "content-length");
+    // any Transfer-Encoding overrides the length, even identity.
+    let has_coding = header::has_header(&unit.headers, "transfer-encoding");
+    if has_length && has_coding {
+        return Err(Error::AmbiguousBody(
+            "both Content-Length and Transfer-Encoding".to_string(),
+        ));
+    }
+    // the compressed length isn't known up front, so a gzip body must be chunked.
+    if unit.is_gzip && (has_length || !unit.is_chunked) {
+        return Err(Error::AmbiguousBody(
+            "gzip body with Content-Length or without chunked Transfer-Encoding".to_string(),
+        ));
+    }
+    if header::content_length(&unit.headers).is_err() {
+        return Err(Error::AmbiguousBody(
+            "conflicting or invalid Content-Length".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Methods where the server expects a body, and thus a `Content-Length: 0`
+/// even when the body is empty.
+fn method_expects_body(method: &str) -> bool {
+    ["POST", "PUT", "PATCH"]
+        .iter()
+        .any(|m| m.eq_ignore_ascii_case(method))
 }
 
 #[cfg(feature = "cookie")]
-fn extract_cookies(state: &std::sync::Mutex<Option<AgentState>>, url: &Url) -> Vec<Header> {
+fn extract_cookies(
+    state: &std::sync::Mutex<Option<AgentState>>,
+    url: &Url,
+    headers: &[Header],
+) -> Vec<Header> {
     let state = state.lock().unwrap();
     let is_secure = url.scheme().eq_ignore_ascii_case("https");
-    let hostname = url.host_str().unwrap_or(DEFAULT_HOST).to_string();
+    let hostname = cookie_host(url, headers).to_string();
 
     match state.as_ref().map(|state| &state.jar) {
         None => vec![],
@@ -212,13 +545,17 @@ fn extract_cookies(state: &std::sync::Mutex<Option<AgentState>>, url: &Url) -> V
 }
 
 #[cfg(not(feature = "cookie"))]
-fn extract_cookies(_state: &std::sync::Mutex<Option<AgentState>>, url: &Url) -> Vec<Header> {
+fn extract_cookies(
+    _state: &std::sync::Mutex<Option<AgentState>>,
+    url: &Url,
+    _headers: &[Header],
+) -> Vec<Header> {
     vec![]
 }
 
 // TODO check so cookies can't be set for tld:s
 #[cfg(feature = "cookie")]
-fn match_cookies<'a>(jar: &'a CookieJar, domain: &str, path: &str, is_secure: bool) -> Vec<Header> {
+fn match_cookies(jar: &CookieJar, domain: &str, path: &str, is_secure: bool) -> Vec<Header> {
     jar.iter()
         .filter(|c| {
             // if there is a domain, it must be matched.
@@ -263,22 +600,65 @@ fn connect_socket(unit: &Unit, use_pooled: bool) -> Result<(Stream, bool), Error
         let state = &mut unit.agent.lock().unwrap();
         if let Some(agent) = state.as_mut() {
             if let Some(stream) = agent.pool.try_get_connection(&unit.url) {
+                stream.set_unit_timeouts(unit);
                 return Ok((stream, true));
             }
         }
     }
     let stream = match unit.url.scheme() {
-        "http" => stream::connect_http(&unit),
-        "https" => connect_https(&unit),
-        "test" => connect_test(&unit),
+        "http" => stream::connect_http(unit),
+        "https" => connect_https(unit),
+        "test" => connect_test(unit),
         _ => Err(Error::UnknownScheme(unit.url.scheme().to_string())),
     };
     Ok((stream?, false))
 }
 
-/// Send request line + headers (all up until the body).
+/// The path of the request line. `OPTIONS *`, a request about the server
+/// rather than some resource, is made with the url path `/*`.
+fn request_path(unit: &Unit) -> &str {
+    let path = unit.url.path();
+    if unit.method.eq_ignore_ascii_case("OPTIONS") && path == "/*" && unit.query_string.is_empty() {
+        "*"
+    } else {
+        path
+    }
+}
+
+/// The `Host` header value for an url. The port is only included when it's
+/// not the default for the scheme.
+pub(crate) fn host_header(url: &Url) -> String {
+    let host = url.host().unwrap();
+    // the url only has a port when it's not the default.
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+/// The host cookies are matched against and saved for. When the user has set
+/// a `Host` header, that is the host the server answers as, not the url host.
+#[cfg(feature = "cookie")]
+fn cookie_host<'a>(url: &'a Url, headers: &'a [Header]) -> &'a str {
+    match header::get_header(headers, "host") {
+        Some(host) => match host.rfind(':') {
+            // drop the port, but not the end of an IPv6 address like "[::1]".
+            Some(index) if !host.ends_with(']') => &host[..index],
+            _ => host,
+        },
+        None => url.host_str().unwrap_or(DEFAULT_HOST),
+    }
+}
+
+/// Send request line + headers (all up until the body), followed by a small body
+/// if there is one.
 #[allow(clippy::write_with_newline)]
-fn send_prelude(unit: &Unit, stream: &mut Stream, redir: bool) -> IoResult<()> {
+fn send_prelude<W: Write>(
+    unit: &Unit,
+    stream: &mut W,
+    redir: bool,
+    small_body: Option<&[u8]>,
+) -> IoResult<()> {
     //
 
     // build into a buffer and send in one go.
@@ -289,13 +669,13 @@ fn send_prelude(unit: &Unit, stream: &mut Stream, redir: bool) -> IoResult<()> {
         prelude,
         "{} {}{} HTTP/1.1\r\n",
         unit.method,
-        unit.url.path(),
+        request_path(unit),
         &unit.query_string
     )?;
 
     // host header if not set by user.
     if !header::has_header(&unit.headers, "host") {
-        write!(prelude, "Host: {}\r\n", unit.url.host().unwrap())?;
+        write!(prelude, "Host: {}\r\n", host_header(&unit.url))?;
     }
     if !header::has_header(&unit.headers, "user-agent") {
         write!(prelude, "User-Agent: ureq\r\n")?;
@@ -314,6 +694,10 @@ fn send_prelude(unit: &Unit, stream: &mut Stream, redir: bool) -> IoResult<()> {
     // finish
     write!(prelude, "\r\n")?;
 
+    if let Some(bytes) = small_body {
+        prelude.extend_from_slice(bytes);
+    }
+
     // write all to the wire
     stream.write_all(&prelude[..])?;
 
@@ -335,21 +719,115 @@ fn save_cookies(unit: &Unit, resp: &Response) {
 
     // only lock if we know there is something to process
     let state = &mut unit.agent.lock().unwrap();
-    if let Some(add_jar) = state.as_mut().map(|state| &mut state.jar) {
+    if let Some(state) = state.as_mut() {
+        let host = cookie_host(&unit.url, &unit.headers);
+        let is_secure = unit.url.scheme().eq_ignore_ascii_case("https");
         for raw_cookie in cookies.iter() {
-            let to_parse = if raw_cookie.to_lowercase().contains("domain=") {
-                (*raw_cookie).to_string()
-            } else {
-                let host = &unit.url.host_str().unwrap_or(DEFAULT_HOST).to_string();
-                format!("{}; Domain={}", raw_cookie, host)
-            };
-            match Cookie::parse_encoded(&to_parse[..]) {
+            match Cookie::parse_encoded(&raw_cookie[..]) {
                 Err(_) => (), // ignore unparseable cookies
-                Ok(cookie) => {
-                    let cookie = cookie.into_owned();
-                    add_jar.add(cookie)
+                Ok(cookie) if cookie_prefix_ok(&cookie, is_secure) => {
+                    state.add_cookie(cookie.into_owned(), Some(host))
                 }
+                Ok(_) => (), // ignore cookies that break their name prefix rules
             }
         }
     }
 }
+
+/// Cookies named `__Secure-` must be secure and set over https. Cookies named
+/// `__Host-` must in addition be for the host only, with the path `/`.
+#[cfg(feature = "cookie")]
+fn cookie_prefix_ok(cookie: &Cookie, is_secure: bool) -> bool {
+    let name = cookie.name().to_ascii_lowercase();
+    let secure = is_secure && cookie.secure().unwrap_or(false);
+    if name.starts_with("__secure-") {
+        secure
+    } else if name.starts_with("__host-") {
+        secure && cookie.domain().is_none() && cookie.path() == Some("/")
+    } else {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the writes to it.
+    #[derive(Default)]
+    struct Writes {
+        count: usize,
+        bytes: Vec<u8>,
+    }
+
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            self.count += 1;
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+
+    /// Send the prelude for a POST of the payload, returning the writes and
+    /// what is left of the body.
+    fn send_post(payload: Payload) -> (Writes, Vec<u8>) {
+        let req = crate::post("http://example.com/").build();
+        let url = Url::parse("http://example.com/").unwrap();
+        let mut body = payload.into_read();
+        let unit = Unit::new(&req, "POST", &url, true, &body);
+        let small_body = take_small_body(&unit, &mut body).unwrap();
+        let mut writes = Writes::default();
+        send_prelude(&unit, &mut writes, false, small_body.as_deref()).unwrap();
+        let mut rest = vec![];
+        body.reader.read_to_end(&mut rest).unwrap();
+        (writes, rest)
+    }
+
+    #[test]
+    fn small_body_sent_with_prelude() {
+        let (writes, rest) = send_post(Payload::Text("hello".into(), "utf-8".into()));
+        assert_eq!(writes.count, 1);
+        let s = String::from_utf8(writes.bytes).unwrap();
+        assert!(s.starts_with("POST / HTTP/1.1\r\n"));
+        assert!(s.ends_with("\r\n\r\nhello"));
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn large_body_sent_after_prelude() {
+        let (writes, rest) = send_post(Payload::Bytes(vec![b'x'; SMALL_BODY_SIZE + 1]));
+        assert_eq!(writes.count, 1);
+        assert!(writes.bytes.ends_with(b"\r\n\r\n"));
+        assert_eq!(rest.len(), SMALL_BODY_SIZE + 1);
+    }
+
+    #[cfg(feature = "cookie")]
+    fn prefix_ok(raw: &str, is_secure: bool) -> bool {
+        cookie_prefix_ok(&Cookie::parse(raw).unwrap(), is_secure)
+    }
+
+    #[test]
+    #[cfg(feature = "cookie")]
+    fn cookie_secure_prefix() {
+        assert!(prefix_ok("__Secure-id=1; Secure", true));
+        assert!(!prefix_ok("__Secure-id=1; Secure", false));
+        assert!(!prefix_ok("__Secure-id=1", true));
+    }
+
+    #[test]
+    #[cfg(feature = "cookie")]
+    fn cookie_host_prefix() {
+        assert!(prefix_ok("__Host-id=1; Secure; Path=/", true));
+        assert!(!prefix_ok(
+            "__Host-id=1; Secure; Path=/; Domain=example.com",
+            true
+        ));
+        assert!(!prefix_ok("__Host-id=1; Secure; Path=/app", true));
+        assert!(!prefix_ok("__Host-id=1; Secure", true));
+        assert!(!prefix_ok("__Host-id=1; Path=/", true));
+        assert!(prefix_ok("id=1", false));
+    }
+}
@@ -11,3 +11,15 @@ fn tls_connection_close() {
     assert_eq!(resp.status(), 404);
     resp.into_reader().read_to_end(&mut vec![]).unwrap();
 }
+
+// there is no way to make the client trust a local self-signed server, so this
+// needs the network.
+#[cfg(feature = "tls")]
+#[test]
+#[ignore = "needs network access to example.com"]
+fn tls_info() {
+    let resp = ureq::get("https://example.com/").call();
+    let info = resp.tls_info().expect("tls info");
+    assert!(info.version() == "TLSv1.2" || info.version() == "TLSv1.3");
+    assert!(!info.cipher_suite().is_empty());
+}